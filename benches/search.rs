@@ -0,0 +1,133 @@
+//! Benchmarks of the search hot paths, run with `cargo bench`
+//!
+//! Positions and searches are seeded, so the numbers of two runs are comparable
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use project::{
+    game::{GameState, MetaMove, PossibleMoves},
+    mcts::MonteCarloSync,
+    players::RandomPlayer,
+    Player,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Up to `plies` seeded random moves, fewer if the game ends before
+fn random_moves(plies: usize, seed: u64) -> Vec<MetaMove> {
+    let mut player = RandomPlayer::with_seed(seed);
+    let mut state = GameState::new();
+    let mut moves = vec![];
+    while moves.len() < plies && state.outcome().is_none() {
+        let meta_move = player.get_move(state.clone());
+        state.set(meta_move).unwrap();
+        moves.push(meta_move);
+    }
+    moves
+}
+
+fn random_position(plies: usize, seed: u64) -> GameState {
+    let mut state = GameState::new();
+    for meta_move in random_moves(plies, seed) {
+        state.set(meta_move).unwrap();
+    }
+    state
+}
+
+fn bench_possible_moves(c: &mut Criterion) {
+    let positions = [
+        ("empty", GameState::new()),
+        // The reply has to go to the board the first move sends to
+        ("forced board", GameState::from_move_list("4.4 4.0").unwrap()),
+        ("midgame", random_position(30, 1)),
+        ("endgame", random_position(60, 2)),
+    ];
+
+    let mut group = c.benchmark_group("get_possible_moves");
+    for (name, state) in positions {
+        let possible_moves = &mut PossibleMoves::new();
+        let next_move = &mut MetaMove::new_empty();
+        group.bench_function(name, |b| b.iter(|| {
+            black_box(&state).get_possible_moves(possible_moves, next_move);
+            black_box(possible_moves.len())
+        }));
+    }
+    group.finish();
+}
+
+fn bench_mcts(c: &mut Criterion) {
+    let state = GameState::from_move_list("4.4 4.0").unwrap();
+    // A new player for every search, a reused one would search on in its old tree
+    c.bench_function("mcts 1000 iterations", |b| b.iter_batched(
+        || MonteCarloSync::with_seed(1000, 7),
+        |mut player| player.get_move(state.clone()),
+        BatchSize::SmallInput,
+    ));
+}
+
+fn bench_make_unmake(c: &mut Criterion) {
+    let moves = random_moves(usize::MAX, 3);
+
+    c.bench_function("make_move unmake_move", |b| b.iter(|| {
+        let mut state = GameState::new();
+        for &meta_move in &moves {
+            state.make_move_unchecked(meta_move);
+        }
+        while state.unmake_move().is_some() {}
+        black_box(state)
+    }));
+}
+
+/// Random playouts from the empty board, once with the checked `set` and once with `set_unchecked`
+fn bench_playout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("playout");
+    group.bench_function("set", |b| {
+        let mut rng = StdRng::seed_from_u64(4);
+        b.iter(|| {
+            let mut state = GameState::new();
+            let possible_moves = &mut PossibleMoves::new();
+            let next_move = &mut MetaMove::new_empty();
+            loop {
+                state.get_possible_moves(possible_moves, next_move);
+                if possible_moves.is_empty() {
+                    break;
+                }
+                state.set(possible_moves[rng.gen_range(0..possible_moves.len())]).unwrap();
+            }
+            black_box(state.get_winner())
+        })
+    });
+    group.bench_function("set_unchecked", |b| {
+        let mut rng = StdRng::seed_from_u64(4);
+        b.iter(|| {
+            let mut state = GameState::new();
+            let possible_moves = &mut PossibleMoves::new();
+            let next_move = &mut MetaMove::new_empty();
+            loop {
+                state.get_possible_moves(possible_moves, next_move);
+                if possible_moves.is_empty() {
+                    break;
+                }
+                state.set_unchecked(possible_moves[rng.gen_range(0..possible_moves.len())]);
+            }
+            black_box(state.get_winner())
+        })
+    });
+    group.finish();
+}
+
+/// Cloning a midgame position, once on its own and once followed by a move on the copy
+fn bench_clone(c: &mut Criterion) {
+    let state = random_position(30, 1);
+    let meta_move = state.legal_moves()[0];
+
+    let mut group = c.benchmark_group("clone");
+    group.bench_function("clone", |b| b.iter(|| black_box(&state).clone()));
+    group.bench_function("clone and set", |b| b.iter(|| {
+        let mut copy = black_box(&state).clone();
+        copy.set_unchecked(meta_move);
+        copy
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_possible_moves, bench_mcts, bench_make_unmake, bench_playout, bench_clone);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+//! A custom bot playing against the alpha-beta engine through the public API
+//! 
+//! Run with `cargo run --example custom_player`
+
+use project::{players::AlphaBetaPlayer, Game, GameState, MetaMove, Player};
+
+/// Takes a center cell whenever it can, otherwise the first legal move
+struct CenterPlayer;
+
+impl Player for CenterPlayer {
+    fn get_move(&mut self, board: GameState) -> MetaMove {
+        let moves = board.legal_moves();
+        let center = moves.into_iter().find(|meta_move| meta_move.cell() == 4);
+        *center.unwrap_or(&moves[0])
+    }
+}
+
+fn main() {
+    let mut game = Game::new(Box::new(CenterPlayer), Box::new(AlphaBetaPlayer::new(2)));
+    let record = game.play();
+    println!("{} after {} moves", game.board().winner_or_draw_text(), record.moves.len());
+}
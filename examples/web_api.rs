@@ -0,0 +1,15 @@
+//! The calls a browser front end makes, run natively
+//! 
+//! Run with `cargo run --example web_api`
+
+use project::web::WebGame;
+
+fn main() {
+    let mut game = WebGame::new(42);
+    println!("legal moves: {:?}", game.legal_moves());
+    game.apply_move(&[4, 4]).expect("the start position allows every move");
+    while let Some(path) = game.engine_move(500) {
+        println!("engine plays {:?}", path);
+    }
+    println!("{}", game.state().winner_or_draw_text());
+}
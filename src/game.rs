@@ -1,9 +1,19 @@
-use std::{error::Error, fmt, ops::{Index, RangeBounds}, vec};
+
+use std::{error::Error, fmt, io, iter, ops::{Index, RangeBounds}, str::FromStr, vec};
+
+use colored::Colorize;
+use rand::{seq::SliceRandom, Rng};
+#[cfg(test)]
+use rand::{rngs::StdRng, SeedableRng};
 
 // #############################
 // Change these value to change the depth of the game
 // The depth of the game is the number of boards that are nested in each other
    const META_DEPTH: usize = 2;
+// Width of a single board and the number of markers in a row needed to win it
+// The board-4x4 feature switches to 4x4 boards without editing the file
+pub const BOARD_SIZE: usize = if cfg!(feature = "board-4x4") { 4 } else { 3 };
+pub const WIN_LENGTH: usize = 3;
 // #############################
 
 
@@ -12,19 +22,87 @@ use std::{error::Error, fmt, ops::{Index, RangeBounds}, vec};
 // #      Fixed Constants      #
 // #                           #
 // #############################
-pub const BOARD_SIZE: usize = 3;
 pub const BOARD_SIZE_SQUARED: usize = BOARD_SIZE * BOARD_SIZE;
 pub const META_SIZE: usize = BOARD_SIZE_SQUARED.pow(META_DEPTH as u32);
-pub const DISPLAY_SIZE: usize = Board::calculate_display_size();
+pub const DISPLAY_SIZE: usize = Board::calculate_display_size(META_DEPTH);
+// Version of the opening book key format, bump it when the key changes
+pub const BOOK_KEY_VERSION: u8 = 1;
+// Storage of the fields of one player on a single bit board
+pub type Bits = u32;
+// All fields of a single bit board
+const FULL_BOARD: Bits = Bits::MAX >> (Bits::BITS as usize - BOARD_SIZE_SQUARED);
+const WINNING_LINES: usize = winning_lines(BOARD_SIZE, WIN_LENGTH);
 // Winning positions for a single bit board
-const WINNING_POSITIONS: [u16; 8] = [
-    0b111_000_000, 0b000_111_000, 0b000_000_111, // Zeilen
-    0b100_100_100, 0b010_010_010, 0b001_001_001, // Spalten
-    0b100_010_001, 0b001_010_100, // Diagonalen
-];
+const WINNING_POSITIONS: [Bits; WINNING_LINES] = winning_positions(BOARD_SIZE, WIN_LENGTH);
+const _: () = assert!(BOARD_SIZE_SQUARED <= Bits::BITS as usize, "a board has to fit into Bits");
+const _: () = assert!(WINNING_LINES <= u32::BITS as usize, "live lines are a u32 bitmask");
+const _: () = assert!(WIN_LENGTH <= BOARD_SIZE && WIN_LENGTH > 0);
+
+/// Number of winning lines of length `win_length` on a board of `size` x `size`
+const fn winning_lines(size: usize, win_length: usize) -> usize {
+    let starts = size - win_length + 1;
+    2 * size * starts + 2 * starts * starts
+}
+
+/// Masks of all winning lines, `LINES` has to be `winning_lines(size, win_length)`
+/// 
+/// Rows from the last to the first one, columns from the last to the first one,
+/// then the diagonals and the anti-diagonals
+const fn winning_positions<const LINES: usize>(size: usize, win_length: usize) -> [Bits; LINES] {
+    let starts = size - win_length + 1;
+    let mut positions = [0; LINES];
+    let mut count = 0;
+
+    // (row, column) of the first field and (row, column) step of every line
+    let mut line = size;
+    while line > 0 {
+        line -= 1;
+        let mut start = 0;
+        while start < starts {
+            positions[count] = line_mask(size, win_length, (line, start), (0, 1));
+            count += 1;
+            start += 1;
+        }
+    }
+    let mut line = size;
+    while line > 0 {
+        line -= 1;
+        let mut start = 0;
+        while start < starts {
+            positions[count] = line_mask(size, win_length, (start, line), (1, 0));
+            count += 1;
+            start += 1;
+        }
+    }
+    let mut row = 0;
+    while row < starts {
+        let mut col = 0;
+        while col < starts {
+            positions[count] = line_mask(size, win_length, (row, col), (1, 1));
+            positions[count + starts * starts] = line_mask(size, win_length, (row, size - 1 - col), (1, -1));
+            count += 1;
+            col += 1;
+        }
+        row += 1;
+    }
+    positions
+}
 
+/// Mask of `win_length` fields from `(row, col)` in the direction `(row_step, col_step)`
+const fn line_mask(size: usize, win_length: usize, (row, col): (usize, usize), (row_step, col_step): (isize, isize)) -> Bits {
+    let mut mask = 0;
+    let mut i = 0;
+    while i < win_length {
+        let r = row as isize + i as isize * row_step;
+        let c = col as isize + i as isize * col_step;
+        mask |= 1 << (r as usize * size + c as usize);
+        i += 1;
+    }
+    mask
+}
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlayerMarker {
     X,
     O,
@@ -33,7 +111,7 @@ pub enum PlayerMarker {
 }
 
 impl PlayerMarker {
-    pub fn to_char(&self) -> char {
+    pub fn to_char(self) -> char {
         match self {
             PlayerMarker::X => 'X',
             PlayerMarker::O => 'O',
@@ -42,7 +120,7 @@ impl PlayerMarker {
         }
     }
 
-    pub fn to_other(&self) -> Self {
+    pub fn to_other(self) -> Self {
         match self {
             PlayerMarker::X => PlayerMarker::O,
             PlayerMarker::O => PlayerMarker::X,
@@ -52,18 +130,73 @@ impl PlayerMarker {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct InvalidMoveError {
-    pub message: String,
+/// Which moves are allowed
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ruleset {
+    /// The last move sends the opponent to the board of its cell
+    #[default]
+    Standard,
+    /// Every open cell can be played, regardless of the last move
+    Free,
+    /// Like `Standard`, except after a move that wins its board the opponent is sent
+    /// to that board. It is decided, so the opponent may play on any open board.
+    SendToClaimed,
+}
+
+/// How a sub board that is full without a winner counts on its parent board
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawnBoardRule {
+    /// Out of play, but the parent cell stays empty, so the parent never fills up
+    /// and is only drawn once no moves are left
+    #[default]
+    Neutral,
+    /// Counts as won by both players on the lines through it
+    CountsForBoth,
+    /// Blocks the lines through it, and the parent is drawn once all its sub boards are decided
+    CountsForNeither,
+}
+
+/// Result of a finished game
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameOutcome {
+    /// X or O
+    Win(PlayerMarker),
+    Draw,
+}
+
+/// Why a move or an index path was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidMove {
+    /// the cell already has a marker
+    CellOccupied,
+    /// the board of the move, or a board around it, is won or full
+    BoardDecided,
+    /// the move doesn't go to the board the last move sends to
+    WrongForcedBoard,
+    /// an index is not a cell of a board, or the path is longer than `META_DEPTH`
+    OutOfBounds,
+    /// the path ends before it reaches a cell
+    IndexTooShort,
+    /// text or data that doesn't describe a move or a position
+    Malformed(String),
 }
 
-impl fmt::Display for InvalidMoveError {
+impl fmt::Display for InvalidMove {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.message)
+        match self {
+            InvalidMove::CellOccupied => write!(f, "The cell is already taken"),
+            InvalidMove::BoardDecided => write!(f, "The board is already decided"),
+            InvalidMove::WrongForcedBoard => write!(f, "The move has to go to the board the last move sends to"),
+            InvalidMove::OutOfBounds => write!(f, "Index out of range"),
+            InvalidMove::IndexTooShort => write!(f, "The index ends before a cell"),
+            InvalidMove::Malformed(message) => write!(f, "{}", message),
+        }
     }
 }
 
-impl Error for InvalidMoveError {}
+impl Error for InvalidMove {}
 
 // #############################
 // #                           #
@@ -81,6 +214,7 @@ pub struct MetaMove {
 }
 
 impl MetaMove {
+    /// Panics unless there is one index per level, use `try_new` for untrusted input
     pub fn new(absolute_index: &[usize]) -> Self {
         if absolute_index.len() > META_DEPTH {
             panic!("Invalid index length");
@@ -91,6 +225,19 @@ impl MetaMove {
         }
     }
 
+    /// Checked `new`, needs `META_DEPTH` indices each in `0..BOARD_SIZE_SQUARED`
+    pub fn try_new(absolute_index: &[usize]) -> Result<Self, InvalidMove> {
+        if absolute_index.len() < META_DEPTH {
+            return Err(InvalidMove::IndexTooShort);
+        }
+        let mut meta_move = MetaMove::new_empty();
+        for &index in absolute_index {
+            meta_move.try_push(index)?;
+        }
+        meta_move.clear();
+        Ok(meta_move)
+    }
+
     pub fn clear(&mut self) {
         self.index = 0;
     }
@@ -110,6 +257,15 @@ impl MetaMove {
         self.index += 1;
     }
 
+    /// Checked `push`, fails if the index is full or `index` is not a cell of a board
+    pub fn try_push(&mut self, index: usize) -> Result<(), InvalidMove> {
+        if self.index >= META_DEPTH || index >= BOARD_SIZE_SQUARED {
+            return Err(InvalidMove::OutOfBounds);
+        }
+        self.push(index);
+        Ok(())
+    }
+
     pub fn pop(&mut self) -> usize {
         self.index -= 1;
         self.absolute_index[self.index]
@@ -124,7 +280,117 @@ impl MetaMove {
         new_index.rotate_left(1);
         MetaMove::new(new_index.as_slice())    
     }
+
+    /// Index path of the innermost board the move is played in
+    pub fn board(&self) -> &[usize] {
+        &self.absolute_index[..META_DEPTH - 1]
+    }
+
+    /// Index of the cell inside the innermost board
+    pub fn cell(&self) -> usize {
+        self.absolute_index[META_DEPTH - 1]
+    }
+
+    /// Inverse of `cell_index`
+    pub fn from_cell_index(cell_index: usize) -> MetaMove {
+        let mut absolute_index = [0; META_DEPTH];
+        let mut cell_index = cell_index;
+        for index in absolute_index.iter_mut().rev() {
+            *index = cell_index % BOARD_SIZE_SQUARED;
+            cell_index /= BOARD_SIZE_SQUARED;
+        }
+        MetaMove::new(&absolute_index)
+    }
+
+    /// Apply one of the 8 symmetries of the square to every nested index
+    /// 
+    /// 0: identity, 1-3: rotations by 90°, 180°, 270°,
+    /// 4: horizontal flip, 5: vertical flip, 6: transpose, 7: anti-transpose
+    pub fn transform(&self, symmetry: usize) -> MetaMove {
+        MetaMove::new(&self.absolute_index.map(|index| transform_index(index, symmetry)))
+    }
+
+    /// The smallest move by `cell_index` among the moves symmetric to this one in the position
+    /// 
+    /// Only symmetries that leave the position unchanged count, see `GameState::canonical_moves`
+    pub fn canonical(&self, state: &GameState) -> MetaMove {
+        canonical_under(*self, &state.symmetries())
+    }
+
+    /// Position of the cell in the nested order of all cells
+    /// 
+    /// \[a, b] -> a * 9 + b
+    pub fn cell_index(&self) -> usize {
+        self.absolute_index
+            .iter()
+            .fold(0, |acc, &index| acc * BOARD_SIZE_SQUARED + index)
+    }
+}
+
+/// The smallest move by `cell_index` the symmetries map the move to
+fn canonical_under(meta_move: MetaMove, symmetries: &[usize]) -> MetaMove {
+    symmetries
+        .iter()
+        .map(|&symmetry| meta_move.transform(symmetry))
+        .min_by_key(MetaMove::cell_index)
+        .unwrap_or(meta_move)
+}
+
+/// Map an index of a single board under one of the 8 symmetries, see `MetaMove::transform`
+fn transform_index(index: usize, symmetry: usize) -> usize {
+    let last = BOARD_SIZE - 1;
+    let (row, col) = (index / BOARD_SIZE, index % BOARD_SIZE);
+    let (row, col) = match symmetry {
+        0 => (row, col),
+        1 => (col, last - row),
+        2 => (last - row, last - col),
+        3 => (last - col, row),
+        4 => (row, last - col),
+        5 => (last - row, col),
+        6 => (col, row),
+        7 => (last - col, last - row),
+        _ => panic!("Invalid symmetry"),
+    };
+    row * BOARD_SIZE + col
+}
+
+/// Dotted index path, e.g. `7.4`
+impl fmt::Display for MetaMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, index) in self.absolute_index.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a dotted index path as printed by `Display`, see `MetaMove::from_coords`
+impl FromStr for MetaMove {
+    type Err = InvalidMove;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MetaMove::from_coords(s)
+    }
 }
+
+impl MetaMove {
+    /// Parse a dotted index path, the outermost board first, e.g. `7.4`
+    /// 
+    /// Needs one index in `0..BOARD_SIZE_SQUARED` per level of `META_DEPTH`
+    pub fn from_coords(s: &str) -> Result<MetaMove, InvalidMove> {
+        let invalid = || InvalidMove::Malformed(format!("Invalid move '{}'", s));
+
+        let absolute_index = s
+            .split('.')
+            .map(|part| part.parse::<usize>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        MetaMove::try_new(&absolute_index).map_err(|_| invalid())
+    }
+}
+
 // #############################
 // #                           #
 // #       PossibleMoves       #
@@ -133,7 +399,9 @@ impl MetaMove {
 
 /// PossibleMoves is a collection of MetaMoves that are possible to play
 /// 
-/// Initialized with a fixed size of META_SIZE for performance reasons
+/// Initialized with a fixed size of META_SIZE for performance reasons.
+/// META_SIZE is the number of all cells at every depth, so even a free choice
+/// of the board on an empty game fits
 pub struct PossibleMoves {
     moves: [MetaMove; META_SIZE],
     index: usize,
@@ -163,6 +431,29 @@ impl PossibleMoves {
     pub fn is_empty(&self) -> bool {
         self.index == 0
     }
+
+    /// Keep only the moves the predicate accepts, in their order
+    pub fn retain(&mut self, mut keep: impl FnMut(&MetaMove) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.index {
+            if keep(&self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
+        self.index = kept;
+    }
+
+    /// Shuffle the possible moves in place, leaving the unused part of the array untouched
+    pub fn shuffle<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.moves[..self.index].shuffle(rng);
+    }
+}
+
+impl Default for PossibleMoves {
+    fn default() -> Self {
+        PossibleMoves::new()
+    }
 }
 
 impl<'a> IntoIterator for &'a PossibleMoves {
@@ -171,7 +462,7 @@ impl<'a> IntoIterator for &'a PossibleMoves {
 
     fn into_iter(self) -> Self::IntoIter {
         PossibleMovesIterator {
-            possible_moves: &self,
+            possible_moves: self,
             current_index: 0,
         }
     }
@@ -220,11 +511,57 @@ impl Index<usize> for PossibleMoves {
 // #                           #
 // #############################
 
-/// BitBoard represents a single board with 9 fields
+/// Winner of a single board given the fields of both players
+/// 
+/// A full board without a line is a draw, independent of the board size so sizes
+/// other than `BOARD_SIZE` can be checked as well
+fn find_winner(x: Bits, o: Bits, full_board: Bits, winning_positions: &[Bits]) -> PlayerMarker {
+    for &pos in winning_positions.iter() {
+        if x & pos == pos {
+            return PlayerMarker::X;
+        } else if o & pos == pos {
+            return PlayerMarker::O;
+        }
+    }
+    if x | o == full_board { PlayerMarker::Draw } else { PlayerMarker::Empty }
+}
+
+// Number of innermost boards, a move sends the opponent to one of them
+const INNER_BOARDS: usize = META_SIZE / BOARD_SIZE_SQUARED;
+// Random keys of the Zobrist hash, see `GameState::zobrist_hash`
+// X and O of every cell of the innermost boards, won boards follow from the cells
+const ZOBRIST_CELL_KEYS: [u64; 2 * META_SIZE] = zobrist_keys(0);
+// The innermost board the last move sends to
+const ZOBRIST_FORCED_KEYS: [u64; INNER_BOARDS] = zobrist_keys(1);
+// O to move
+const ZOBRIST_SIDE_KEY: u64 = splitmix64(u64::MAX);
+
+/// Fixed pseudo random numbers, a different `stream` gives different numbers
+const fn zobrist_keys<const N: usize>(stream: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = splitmix64(stream << 32 | i as u64);
+        i += 1;
+    }
+    keys
+}
+
+/// Mixes the bits of a counter into a pseudo random number
+const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// BitBoard represents a single board with `BOARD_SIZE_SQUARED` fields
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub struct BitBoard {
-    x: u16,
-    o: u16,
+    x: Bits,
+    o: Bits,
+    /// `find_winner` of the cells, updated on every change
+    winner: PlayerMarker,
 }
 
 impl BitBoard {
@@ -233,7 +570,23 @@ impl BitBoard {
         BitBoard {
             x: 0,
             o: 0,
+            winner: PlayerMarker::Empty,
+        }
+    }
+
+    /// Create a board from the raw bit masks of both players
+    /// 
+    /// The masks must not overlap and may only use the lowest `BOARD_SIZE_SQUARED` bits
+    pub fn from_bits(x: Bits, o: Bits) -> Result<Self, InvalidMove> {
+        if x & o != 0 {
+            return Err(InvalidMove::Malformed(String::from("Masks overlap")));
+        }
+        if (x | o) & !FULL_BOARD != 0 {
+            return Err(InvalidMove::Malformed(String::from("Mask out of range")));
         }
+        let mut board = BitBoard { x, o, winner: PlayerMarker::Empty };
+        board.update_winner();
+        Ok(board)
     }
 
 
@@ -248,11 +601,14 @@ impl BitBoard {
         }
     }
 
-    fn set(&mut self, index: usize, player: PlayerMarker) -> Result<PlayerMarker, InvalidMoveError> {
+    fn set(&mut self, index: usize, player: PlayerMarker) -> Result<PlayerMarker, InvalidMove> {
+        if index >= BOARD_SIZE_SQUARED {
+            return Err(InvalidMove::OutOfBounds);
+        }
         let mask = 1 << index;
 
         if (self.x | self.o) & mask > 0 {
-            return Err(InvalidMoveError { message: String::from("Move was already played") });
+            return Err(InvalidMove::CellOccupied);
         }
 
         match player {
@@ -261,7 +617,22 @@ impl BitBoard {
             _ => {}
         };
 
-        Ok(self.get_winner())
+        self.update_winner();
+        Ok(self.winner)
+    }
+
+    /// Same as `set`, but without checking if the field is already taken
+    fn set_unchecked(&mut self, index: usize, player: PlayerMarker) -> PlayerMarker {
+        let mask = 1 << index;
+
+        match player {
+            PlayerMarker::X => self.x |= mask,
+            PlayerMarker::O => self.o |= mask,
+            _ => {}
+        };
+
+        self.update_winner();
+        self.winner
     }
 
     fn unset(&mut self, index: &[usize]) {
@@ -272,6 +643,7 @@ impl BitBoard {
         let mask = !(1 << index);
         self.x &= mask;
         self.o &= mask;
+        self.update_winner();
     }
 
     fn get_empty_positions(&self, _index: &[usize], possible_moves: &mut PossibleMoves, next_move: &mut MetaMove) {
@@ -283,29 +655,142 @@ impl BitBoard {
             let mask = 1 << i;
             if self.x & mask == 0 && self.o & mask == 0 {
                 next_move.push(i);
-                possible_moves.push(next_move.clone());
+                possible_moves.push(*next_move);
                 next_move.pop();
             }
         }
     }
 
     fn get_winner(&self) -> PlayerMarker {
-        if self.x | self.o == 0b111_111_111 {
-            return PlayerMarker::Draw;
+        self.winner
+    }
+
+    fn update_winner(&mut self) {
+        self.winner = find_winner(self.x, self.o, FULL_BOARD, &WINNING_POSITIONS);
+    }
+
+    fn can_set(&self) -> bool {
+        self.get_winner() == PlayerMarker::Empty
+    }
+
+    fn check_move(&self, index: &[usize]) -> Result<(), InvalidMove> {
+        match index {
+            [] => Err(InvalidMove::IndexTooShort),
+            [index] if *index >= BOARD_SIZE_SQUARED => Err(InvalidMove::OutOfBounds),
+            [_] if !self.can_set() => Err(InvalidMove::BoardDecided),
+            [index] if self.get(*index) != PlayerMarker::Empty => Err(InvalidMove::CellOccupied),
+            [_] => Ok(()),
+            _ => Err(InvalidMove::OutOfBounds),
+        }
+    }
+
+    /// Check if the player has won the board or can still complete a line
+    fn can_win(&self, player: PlayerMarker) -> bool {
+        let winner = self.get_winner();
+        if winner != PlayerMarker::Empty {
+            return winner == player;
+        }
+        self.live_lines(player) != 0
+    }
+
+    /// Bitmask over `WINNING_POSITIONS` of the lines without a marker of the opponent
+    fn live_lines(&self, player: PlayerMarker) -> u32 {
+        let opponent = match player {
+            PlayerMarker::X => self.o,
+            PlayerMarker::O => self.x,
+            _ => return 0,
+        };
+        WINNING_POSITIONS
+            .iter()
+            .enumerate()
+            .filter(|(_, &pos)| opponent & pos == 0)
+            .fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    /// Number of winning lines without a marker of the opponent
+    pub fn open_lines(&self, player: PlayerMarker) -> u8 {
+        self.live_lines(player).count_ones() as u8
+    }
+
+    /// Winner of the board once the player sets the field at index, see `find_winner`
+    fn winner_after(&self, index: usize, player: PlayerMarker) -> PlayerMarker {
+        match player {
+            PlayerMarker::X => find_winner(self.x | 1 << index, self.o, FULL_BOARD, &WINNING_POSITIONS),
+            PlayerMarker::O => find_winner(self.x, self.o | 1 << index, FULL_BOARD, &WINNING_POSITIONS),
+            _ => self.get_winner(),
         }
+    }
+
+    /// Check if setting the field at index would win the board for the player
+    fn is_winning_move(&self, index: usize, player: PlayerMarker) -> bool {
+        let board = match player {
+            PlayerMarker::X => self.x,
+            PlayerMarker::O => self.o,
+            _ => return false,
+        } | 1 << index;
 
         for &pos in WINNING_POSITIONS.iter() {
-            if self.x & pos == pos {
-                return PlayerMarker::X;
-            } else if self.o & pos == pos {
-                return PlayerMarker::O;
+            if board & pos == pos {
+                return true;
             }
         }
-        PlayerMarker::Empty
+        false
     }
 
-    fn can_set(&self) -> bool {
-        self.get_winner() == PlayerMarker::Empty
+    /// Score how valuable the field at index is for the player
+    /// 
+    /// 1 completes a line, 0.75 blocks the opponent, 0.5 creates a fork,
+    /// otherwise up to 0.25 for the number of open lines through the field
+    fn cell_score(&self, index: usize, player: PlayerMarker) -> f32 {
+        let mask = 1 << index;
+        let (own, other) = match player {
+            PlayerMarker::X => (self.x, self.o),
+            PlayerMarker::O => (self.o, self.x),
+            _ => return 0.,
+        };
+        if (own | other) & mask != 0 {
+            return 0.;
+        }
+
+        if self.is_winning_move(index, player) {
+            return 1.;
+        }
+        if self.is_winning_move(index, player.to_other()) {
+            return 0.75;
+        }
+
+        let own = own | mask;
+        let mut open_lines = 0;
+        let mut threats = 0;
+        for &pos in WINNING_POSITIONS.iter() {
+            if pos & mask == 0 || other & pos != 0 {
+                continue;
+            }
+            open_lines += 1;
+            if (own & pos).count_ones() as usize == WIN_LENGTH - 1 {
+                threats += 1;
+            }
+        }
+
+        if threats >= 2 {
+            return 0.5;
+        }
+        0.0625 * open_lines as f32
+    }
+
+    fn fill_threat_map(&self, player: PlayerMarker, map: &mut [f32]) {
+        if !self.can_set() {
+            return;
+        }
+        for (i, score) in map.iter_mut().enumerate() {
+            *score = self.cell_score(i, player);
+        }
+    }
+}
+
+impl Default for BitBoard {
+    fn default() -> Self {
+        BitBoard::new()
     }
 }
 
@@ -318,12 +803,20 @@ impl BitBoard {
 #[derive(Clone, PartialEq)]
 pub struct MetaBoard {
     pub board: BitBoard,
+    /// Owned on purpose: copy-on-write sub boards (`Arc` + `make_mut`) only pay off
+    /// for clones that stay unchanged, and the search doesn't clone anymore, see
+    /// `GameState::make_move`. The `clone` benchmarks measure the owned layout.
     pub sub_boards: Box<[Board; BOARD_SIZE_SQUARED]>,
+    /// sub boards that are full without a winner
+    drawn: Bits,
+    drawn_board_rule: DrawnBoardRule,
+    /// `compute_winner` of `board` and `drawn`, kept up to date by `set` and `unset`
+    winner: PlayerMarker,
 }
 
 impl MetaBoard {
 
-    fn get(&self, index: &[usize]) -> Result<PlayerMarker, InvalidMoveError> {
+    fn get(&self, index: &[usize]) -> Result<PlayerMarker, InvalidMove> {
         if index.len() <= 1 {
             return Ok(self.board.get(index[0]));
         }
@@ -334,19 +827,18 @@ impl MetaBoard {
     /// Set the player marker at the given index
     /// 
     /// Returns the winner of the (current) board if the board is won
-    fn set(&mut self, index: &[usize], player: PlayerMarker) -> Result<PlayerMarker, InvalidMoveError> {
+    fn set(&mut self, index: &[usize], player: PlayerMarker) -> Result<PlayerMarker, InvalidMove> {
         if index.len() <= 1 {
-            return Err(InvalidMoveError {
-                message: "Index is too short".to_string(),
-            });
+            return Err(InvalidMove::IndexTooShort);
         }
 
         let spec_index = index[0];
+        if spec_index >= BOARD_SIZE_SQUARED {
+            return Err(InvalidMove::OutOfBounds);
+        }
         // Check if the board is already won 
         if self.board.get(spec_index) != PlayerMarker::Empty {
-            return Err(InvalidMoveError {
-                message: "Board is already won".to_string(),
-            });
+            return Err(InvalidMove::BoardDecided);
         }
 
         // Set the marker in the sub board
@@ -354,15 +846,33 @@ impl MetaBoard {
         // If the sub board is won, set the marker in the current board
         match sub_board.set(&index[1..], player) {
             Ok(marker) => {
-                if marker != PlayerMarker::Empty {
-                    self.board.set(spec_index, marker)?;
-                }
-                Ok(self.board.get_winner())
+                self.mark_decided(spec_index, marker);
+                Ok(self.get_winner())
             }
             Err(e) => Err(e),
         }
     }
 
+    /// Same as `set`, but without checking if the move is valid
+    fn set_unchecked(&mut self, index: &[usize], player: PlayerMarker) -> PlayerMarker {
+        let spec_index = index[0];
+        let marker = self.sub_boards[spec_index].set_unchecked(&index[1..], player);
+        self.mark_decided(spec_index, marker);
+        self.get_winner()
+    }
+
+    /// Note the result of the sub board, won boards go on `board` and drawn ones on `drawn`
+    fn mark_decided(&mut self, index: usize, marker: PlayerMarker) {
+        match marker {
+            PlayerMarker::X | PlayerMarker::O => {
+                self.board.set_unchecked(index, marker);
+            }
+            PlayerMarker::Draw => self.drawn |= 1 << index,
+            PlayerMarker::Empty => return,
+        }
+        self.update_winner();
+    }
+
     fn unset(&mut self, index: &[usize]) {
         if index.len() <= 1 {
             return;
@@ -371,6 +881,24 @@ impl MetaBoard {
         let sub_board = self.sub_boards.get_mut(spec_index).unwrap();
         sub_board.unset(&index[1..]);
         self.board.unset(&[spec_index]);
+        self.drawn &= !(1 << spec_index);
+        self.update_winner();
+    }
+
+    /// Mark the won sub boards from their cells, bottom up
+    /// 
+    /// Gives the same result as playing the cells, because a decided board gets no more moves
+    fn update_won_boards(&mut self) {
+        self.board = BitBoard::new();
+        self.drawn = 0;
+        for i in 0..BOARD_SIZE_SQUARED {
+            if let Board::MetaBoard(meta_board) = &mut self.sub_boards[i] {
+                meta_board.update_won_boards();
+            }
+            let marker = self.sub_boards[i].get_winner();
+            self.mark_decided(i, marker);
+        }
+        self.update_winner();
     }
 
     /// Get all empty positions in the board
@@ -379,7 +907,7 @@ impl MetaBoard {
     /// 
     /// index: the left shifted index of the last move
     fn get_empty_positions(&self, index: &[usize], possible_moves: &mut PossibleMoves, next_move: &mut MetaMove) {
-        if self.get_winner() != PlayerMarker::Empty {
+        if self.get_winner() != PlayerMarker::Empty || self.is_contested() {
             return;
         }
 
@@ -408,11 +936,130 @@ impl MetaBoard {
     }
 
     fn get_winner(&self) -> PlayerMarker {
-        self.board.get_winner()
+        self.winner
+    }
+
+    fn update_winner(&mut self) {
+        self.winner = self.compute_winner();
+    }
+
+    /// Winner of the meta lines, drawn sub boards count as the `DrawnBoardRule` says
+    fn compute_winner(&self) -> PlayerMarker {
+        self.winner_of(&self.board, self.drawn)
+    }
+
+    /// `compute_winner` for other won and drawn sub boards
+    fn winner_of(&self, board: &BitBoard, drawn: Bits) -> PlayerMarker {
+        let (x, o) = (board.x, board.o);
+        match self.drawn_board_rule {
+            DrawnBoardRule::Neutral => board.get_winner(),
+            DrawnBoardRule::CountsForNeither => match board.get_winner() {
+                PlayerMarker::Empty if x | o | drawn == FULL_BOARD => PlayerMarker::Draw,
+                winner => winner,
+            },
+            DrawnBoardRule::CountsForBoth => {
+                // A line of drawn boards only doesn't belong to anyone
+                for &pos in WINNING_POSITIONS.iter() {
+                    if x & pos != 0 && (x | drawn) & pos == pos {
+                        return PlayerMarker::X;
+                    } else if o & pos != 0 && (o | drawn) & pos == pos {
+                        return PlayerMarker::O;
+                    }
+                }
+                if x | o | drawn == FULL_BOARD { PlayerMarker::Draw } else { PlayerMarker::Empty }
+            }
+        }
+    }
+
+    /// Winner once the player sets the cell, `Empty` while the board stays open
+    fn winner_after(&self, index: &[usize], player: PlayerMarker) -> PlayerMarker {
+        let (mut board, mut drawn) = (self.board, self.drawn);
+        match self.sub_boards[index[0]].winner_after(&index[1..], player) {
+            PlayerMarker::Empty => return self.get_winner(),
+            PlayerMarker::Draw => drawn |= 1 << index[0],
+            marker => {
+                board.set_unchecked(index[0], marker);
+            }
+        }
+        self.winner_of(&board, drawn)
     }
 
     fn can_set(&self) -> bool {
-        self.get_winner() == PlayerMarker::Empty && self.sub_boards.iter().any(|board| board.can_set())
+        self.get_winner() == PlayerMarker::Empty && !self.is_contested() && self.sub_boards.iter().any(|board| board.can_set())
+    }
+
+    /// Check if every line holds sub boards won by both players, so nobody can win this board
+    fn is_contested(&self) -> bool {
+        self.board.live_lines(PlayerMarker::X) == 0 && self.board.live_lines(PlayerMarker::O) == 0
+    }
+
+    fn set_drawn_board_rule(&mut self, rule: DrawnBoardRule) {
+        self.drawn_board_rule = rule;
+        self.update_winner();
+        for sub_board in self.sub_boards.iter_mut() {
+            if let Board::MetaBoard(meta_board) = sub_board {
+                meta_board.set_drawn_board_rule(rule);
+            }
+        }
+    }
+
+    /// Same rules as `get_empty_positions`, without collecting the moves
+    fn check_move(&self, index: &[usize], forced: &[usize]) -> Result<(), InvalidMove> {
+        let Some((&target, index)) = index.split_first() else {
+            return Err(InvalidMove::IndexTooShort);
+        };
+        if target >= BOARD_SIZE_SQUARED {
+            return Err(InvalidMove::OutOfBounds);
+        }
+        if self.get_winner() != PlayerMarker::Empty || self.is_contested() || self.board.get(target) != PlayerMarker::Empty {
+            return Err(InvalidMove::BoardDecided);
+        }
+
+        // Sent to a decided or full board, every open board is allowed
+        let free = match forced.first() {
+            Some(&board) => self.board.get(board) != PlayerMarker::Empty || !self.sub_boards[board].can_set(),
+            None => true,
+        };
+        if free {
+            self.sub_boards[target].check_move(index, &[])
+        } else if forced[0] != target {
+            Err(InvalidMove::WrongForcedBoard)
+        } else {
+            self.sub_boards[target].check_move(index, &forced[1..])
+        }
+    }
+
+    /// Check if the player has won the board or can still complete a line
+    /// 
+    /// A line stays open while every cell is won by the player or is a sub board
+    /// the player can still win. Whose turn it is and the forced board are ignored.
+    fn can_win(&self, player: PlayerMarker) -> bool {
+        let winner = self.get_winner();
+        if winner != PlayerMarker::Empty {
+            return winner == player;
+        }
+        WINNING_POSITIONS.iter().any(|&pos| {
+            (0..BOARD_SIZE_SQUARED)
+                .filter(|i| pos & 1 << i != 0)
+                .all(|i| match self.board.get(i) {
+                    PlayerMarker::Empty if self.drawn & 1 << i != 0 => self.drawn_board_rule == DrawnBoardRule::CountsForBoth,
+                    PlayerMarker::Empty => self.sub_boards[i].can_win(player),
+                    marker => marker == player,
+                })
+        })
+    }
+
+    fn fill_threat_map(&self, player: PlayerMarker, map: &mut [f32]) {
+        if !self.can_set() {
+            return;
+        }
+        let sub_size = map.len() / BOARD_SIZE_SQUARED;
+        for (i, sub_map) in map.chunks_mut(sub_size).enumerate() {
+            // Decided boards can't be played anymore
+            if self.board.get(i) == PlayerMarker::Empty {
+                self.sub_boards[i].fill_threat_map(player, sub_map);
+            }
+        }
     }
     
 }
@@ -444,15 +1091,32 @@ impl Board{
             Board::MetaBoard(MetaBoard {
                 board: BitBoard::new(),
                 sub_boards: Box::new([(); BOARD_SIZE_SQUARED].map(|_| Board::create_board(depth - 1))),
+                drawn: 0,
+                drawn_board_rule: DrawnBoardRule::default(),
+                winner: PlayerMarker::Empty,
             })
         }
     }
 
-    fn set(&mut self, meta_move: &[usize], player: PlayerMarker) -> Result<PlayerMarker, InvalidMoveError> {
-        if meta_move.len() == 0 {
-            return Err(InvalidMoveError {
-                message: "Index is empty".to_string(),
-            });
+    /// Direct sub boards, empty for a bit board
+    pub fn sub_boards(&self) -> &[Board] {
+        match self {
+            Board::BitBoard(_) => &[],
+            Board::MetaBoard(meta_board) => meta_board.sub_boards.as_slice(),
+        }
+    }
+
+    /// Nesting depth of the board, 1 for a single bit board
+    pub fn depth(&self) -> usize {
+        match self {
+            Board::BitBoard(_) => 1,
+            Board::MetaBoard(meta_board) => 1 + meta_board.sub_boards[0].depth(),
+        }
+    }
+
+    fn set(&mut self, meta_move: &[usize], player: PlayerMarker) -> Result<PlayerMarker, InvalidMove> {
+        if meta_move.is_empty() {
+            return Err(InvalidMove::IndexTooShort);
         }
 
         match self {
@@ -461,13 +1125,20 @@ impl Board{
                 if meta_move.len() == 1 {
                     bit_board.set(meta_move[0], player)
                 } else {
-                    Err(InvalidMoveError { message: String::from("Invalid Index") })
+                    Err(InvalidMove::OutOfBounds)
                 }
         }
     }
 
+    fn set_unchecked(&mut self, meta_move: &[usize], player: PlayerMarker) -> PlayerMarker {
+        match self {
+            Board::MetaBoard(meta_board) => meta_board.set_unchecked(meta_move, player),
+            Board::BitBoard(bit_board) => bit_board.set_unchecked(meta_move[0], player),
+        }
+    }
+
     fn unset(&mut self, meta_move: &[usize]) {
-        if meta_move.len() == 0 {
+        if meta_move.is_empty() {
             panic!("Index is empty")
         }
 
@@ -484,8 +1155,8 @@ impl Board{
         }
     }
 
-    fn get(&self, index: &[usize]) -> Result<PlayerMarker, InvalidMoveError> {
-        if index.len() == 0 {
+    fn get(&self, index: &[usize]) -> Result<PlayerMarker, InvalidMove> {
+        if index.is_empty() {
             panic!("Index is empty")
         }
         match self {
@@ -494,7 +1165,7 @@ impl Board{
                 if index.len() == 1 {
                     Ok(bit_board.get(index[0]))
                 } else {
-                    Err(InvalidMoveError { message: String::from("Invalid Index") })
+                    Err(InvalidMove::OutOfBounds)
                 },
         }
     }
@@ -513,6 +1184,62 @@ impl Board{
         }
     }
 
+    /// Check if the cell at `index` can be played when the previous move sends to `forced`
+    pub fn is_legal(&self, index: &[usize], forced: &[usize]) -> bool {
+        self.check_move(index, forced).is_ok()
+    }
+
+    /// Same as `is_legal`, with the reason the move can't be played
+    pub fn check_move(&self, index: &[usize], forced: &[usize]) -> Result<(), InvalidMove> {
+        match self {
+            Board::BitBoard(bit_board) => bit_board.check_move(index),
+            Board::MetaBoard(meta_board) => meta_board.check_move(index, forced),
+        }
+    }
+
+    pub fn can_win(&self, player: PlayerMarker) -> bool {
+        match self {
+            Board::BitBoard(bit_board) => bit_board.can_win(player),
+            Board::MetaBoard(meta_board) => meta_board.can_win(player),
+        }
+    }
+
+    /// Check if no player can win the board anymore
+    pub fn is_dead(&self) -> bool {
+        !self.can_win(PlayerMarker::X) && !self.can_win(PlayerMarker::O)
+    }
+
+    fn fill_threat_map(&self, player: PlayerMarker, map: &mut [f32]) {
+        match self {
+            Board::BitBoard(bit_board) => bit_board.fill_threat_map(player, map),
+            Board::MetaBoard(meta_board) => meta_board.fill_threat_map(player, map),
+        }
+    }
+
+    /// Winner of the board once the player sets the cell, `Empty` while it stays open
+    /// 
+    /// Drawn sub boards count as the `DrawnBoardRule` of their parent says
+    fn winner_after(&self, index: &[usize], player: PlayerMarker) -> PlayerMarker {
+        match self {
+            Board::BitBoard(bit_board) => bit_board.winner_after(index[0], player),
+            Board::MetaBoard(meta_board) => meta_board.winner_after(index, player),
+        }
+    }
+
+    /// Get the bit board containing the field of the index
+    fn get_bit_board(&self, index: &[usize]) -> &BitBoard {
+        match self {
+            Board::BitBoard(bit_board) => bit_board,
+            Board::MetaBoard(meta_board) => meta_board.sub_boards[index[0]].get_bit_board(&index[1..]),
+        }
+    }
+
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
 }
 
 // #############################
@@ -522,16 +1249,18 @@ impl Board{
 // #############################
 
 impl Board {
-    const fn calculate_display_size() -> usize {
+    /// Width and height of the `Display` output of a board nested `depth` times
+    const fn calculate_display_size(depth: usize) -> usize {
         let mut current = 1;
         let mut index = 0;
         loop {
-            if META_DEPTH == index {
+            if depth == index {
                 return current;
             }
             
+            // The sub boards with a separator of `index` columns between neighbours
             if let Some(val) = current.checked_mul(BOARD_SIZE) {
-                if let Some(val2) = val.checked_add(index * 2) {
+                if let Some(val2) = val.checked_add(index * (BOARD_SIZE - 1)) {
                     current = val2;
                 } else {
                     panic!("overflow");
@@ -567,7 +1296,7 @@ impl BitBoard {
                 } else if self.o & mask != 0 {
                     'O'
                 } else {
-                    '-'
+                    '.'
                 };
                 array[top + i][left + j] = symbol;
             }
@@ -575,14 +1304,41 @@ impl BitBoard {
     }
 }
 
+/// Width of a sub board and the gap between two of them on a board of `display_size` at `depth`
+fn sub_board_layout(depth: usize, display_size: usize) -> (usize, usize) {
+    let gap = depth - 1;
+    ((display_size - (BOARD_SIZE - 1) * gap) / BOARD_SIZE, gap)
+}
+
+/// The separator in the gaps between the sub boards of a board at `depth`, relative to its top left
+/// 
+/// `|` between columns, `-` between rows and `+` where they cross. The gaps of
+/// outer boards are wider, so every level of nesting gets lines of its own width.
+/// `None` inside a sub board or outside of the board.
+fn separator_char((row, col): (usize, usize), depth: usize, display_size: usize) -> Option<char> {
+    if row >= display_size || col >= display_size {
+        return None;
+    }
+    let (sub_width, gap) = sub_board_layout(depth, display_size);
+    let in_gap = |offset: usize| offset % (sub_width + gap) >= sub_width;
+    match (in_gap(row), in_gap(col)) {
+        (true, true) => Some('+'),
+        (true, false) => Some('-'),
+        (false, true) => Some('|'),
+        (false, false) => None,
+    }
+}
+
 impl MetaBoard {
     fn fill_board(&self, array: &mut [Vec<char>], (top, left): (usize, usize), depth: usize, display_size: usize) {
-        let sub_size = (display_size - depth * 2) / BOARD_SIZE;
+        let (sub_width, gap) = sub_board_layout(depth, display_size);
+        // Offset of the last row and column of a sub board
+        let sub_size = sub_width - 1;
         for i in 0..BOARD_SIZE {
             for j in 0..BOARD_SIZE {
                 let index: usize = i * BOARD_SIZE + j;
-                let sub_top = top + i * sub_size + i * depth;
-                let sub_left = left + j * sub_size + j * depth;
+                let sub_top = top + i * (sub_width + gap);
+                let sub_left = left + j * (sub_width + gap);
 
                 if self.board.get(index) != PlayerMarker::Empty  {
                     let symbol = self.board.get(index).to_char();
@@ -593,14 +1349,250 @@ impl MetaBoard {
                     array[sub_top + sub_size][sub_left] = symbol;
                     array[sub_top + sub_size / 2][sub_left + sub_size / 2] = symbol;
                 } else {
-                    self.sub_boards[index].fill_board(array, (sub_top, sub_left), depth - 1, sub_size);
+                    self.sub_boards[index].fill_board(array, (sub_top, sub_left), depth - 1, sub_width);
                 }
 
                 // self.sub_boards[index].fill_board(array, (sub_top, sub_left), depth - 1, sub_size);
             }
         }
-    }
-}
+
+        for row in 0..display_size {
+            for col in 0..display_size {
+                if let Some(separator) = separator_char((row, col), depth, display_size) {
+                    array[top + row][left + col] = separator;
+                }
+            }
+        }
+    }
+}
+
+/// Characters the grid lines of the display are drawn with
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DisplayStyle {
+    /// `|`, `-` and `+`, the `Display` output
+    #[default]
+    Ascii,
+    /// box-drawing lines with a frame around the meta board
+    Unicode,
+}
+
+impl DisplayStyle {
+    /// The character of an ASCII separator in this style
+    fn separator(self, symbol: char) -> char {
+        match (self, symbol) {
+            (DisplayStyle::Unicode, '|') => '│',
+            (DisplayStyle::Unicode, '-') => '─',
+            (DisplayStyle::Unicode, '+') => '┼',
+            _ => symbol,
+        }
+    }
+}
+
+/// How `render_with` draws the board, the default matches `Display`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DisplayOptions {
+    pub style: DisplayStyle,
+    /// label the rows and columns with their index on every level, see `coordinate_label`
+    pub coordinates: bool,
+}
+
+impl DisplayOptions {
+    pub fn with_style(mut self, style: DisplayStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn with_coordinates(mut self, coordinates: bool) -> Self {
+        self.coordinates = coordinates;
+        self
+    }
+}
+
+/// Index of the row or column at a display offset on a level of the board, 0 is the outer board
+/// 
+/// Only the middle row or column of a sub board gets the label, so the digit of the
+/// outer board stands above the middle of its sub board. Together the labels of all
+/// levels are the indices of `MetaMove::absolute_index`, a board index is `row * BOARD_SIZE + col`.
+fn coordinate_label(mut offset: usize, level: usize, depth: usize) -> Option<char> {
+    let mut width = Board::calculate_display_size(depth);
+    for current in 0..=level {
+        let (sub_width, gap) = sub_board_layout(depth - current, width);
+        let index = offset / (sub_width + gap);
+        offset %= sub_width + gap;
+        if index >= BOARD_SIZE || offset >= sub_width {
+            return None;
+        }
+        if current == level {
+            return (offset == sub_width / 2).then(|| char::from_digit(index as u32, 10).unwrap());
+        }
+        width = sub_width;
+    }
+    None
+}
+
+impl Board {
+    /// Write the same 2D representation as `Display` row by row
+    /// 
+    /// Every character is looked up on its own, so no 2D array has to be allocated
+    pub fn render(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.render_with(w, DisplayOptions::default())
+    }
+
+    /// Like `render`, drawn with the style and labels of the options
+    /// 
+    /// With coordinates a header row per level labels the columns and a column
+    /// per level left of the board labels the rows, the outer board first
+    pub fn render_with(&self, w: &mut impl io::Write, options: DisplayOptions) -> io::Result<()> {
+        let depth = self.depth();
+        let size = Board::calculate_display_size(depth);
+        let symbol = |row, col| self.display_char((row, col), depth, size).unwrap_or(' ');
+        let framed = options.style == DisplayStyle::Unicode;
+        // The row labels of the levels are separated by a space
+        let margin = if options.coordinates { 2 * depth } else { 0 };
+        // The top and bottom of the frame join the vertical lines that reach them
+        let frame = |row, [left, join, line, right]: [char; 4]| {
+            iter::repeat_n(' ', margin)
+                .chain(iter::once(left))
+                .chain((0..size).map(|col| if symbol(row, col) == '|' { join } else { line }))
+                .chain([right, '\n'])
+                .collect::<String>()
+        };
+
+        if options.coordinates {
+            for level in 0..depth {
+                // Padded like the frame, so every line has the same width
+                let header: String = iter::repeat_n(' ', margin + framed as usize)
+                    .chain((0..size).map(|col| coordinate_label(col, level, depth).unwrap_or(' ')))
+                    .chain(iter::repeat_n(' ', framed as usize))
+                    .chain(iter::once('\n'))
+                    .collect();
+                w.write_all(header.as_bytes())?;
+            }
+        }
+        if framed {
+            w.write_all(frame(0, ['┌', '┬', '─', '┐']).as_bytes())?;
+        }
+        let mut line = String::with_capacity(4 * (margin + size + 3));
+        for row in 0..size {
+            line.clear();
+            if options.coordinates {
+                for level in 0..depth {
+                    line.push(coordinate_label(row, level, depth).unwrap_or(' '));
+                    line.push(' ');
+                }
+            }
+            let is_separator_row = symbol(row, 0) == '-';
+            if framed {
+                line.push(if is_separator_row { '├' } else { '│' });
+            }
+            for col in 0..size {
+                line.push(options.style.separator(symbol(row, col)));
+            }
+            if framed {
+                line.push(if is_separator_row { '┤' } else { '│' });
+            }
+            line.push('\n');
+            w.write_all(line.as_bytes())?;
+        }
+        if framed {
+            w.write_all(frame(size - 1, ['└', '┴', '─', '┘']).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// The character `fill_board` writes at a position relative to the top left of the board
+    fn display_char(&self, position: (usize, usize), depth: usize, display_size: usize) -> Option<char> {
+        match self {
+            Board::BitBoard(bitboard) => bitboard.display_char(position),
+            Board::MetaBoard(metaboard) => metaboard.display_char(position, depth, display_size),
+        }
+    }
+}
+
+impl BitBoard {
+    fn display_char(&self, (row, col): (usize, usize)) -> Option<char> {
+        if row >= BOARD_SIZE || col >= BOARD_SIZE {
+            return None;
+        }
+        let symbol = match self.get(row * BOARD_SIZE + col) {
+            PlayerMarker::Empty => '.',
+            marker => marker.to_char(),
+        };
+        Some(symbol)
+    }
+}
+
+impl MetaBoard {
+    fn display_char(&self, (row, col): (usize, usize), depth: usize, display_size: usize) -> Option<char> {
+        if let Some(separator) = separator_char((row, col), depth, display_size) {
+            return Some(separator);
+        }
+        let (sub_width, gap) = sub_board_layout(depth, display_size);
+        let sub_size = sub_width - 1;
+        let mut symbol = None;
+        // Later sub boards overwrite earlier ones, just like in fill_board
+        for i in 0..BOARD_SIZE {
+            for j in 0..BOARD_SIZE {
+                let index: usize = i * BOARD_SIZE + j;
+                let sub_top = i * (sub_width + gap);
+                let sub_left = j * (sub_width + gap);
+                if row < sub_top || col < sub_left {
+                    continue;
+                }
+                let position = (row - sub_top, col - sub_left);
+
+                let sub_symbol = if self.board.get(index) != PlayerMarker::Empty {
+                    let marked = [(0, 0), (0, sub_size), (sub_size, sub_size), (sub_size, 0), (sub_size / 2, sub_size / 2)];
+                    marked.contains(&position).then(|| self.board.get(index).to_char())
+                } else {
+                    self.sub_boards[index].display_char(position, depth - 1, sub_width)
+                };
+                if sub_symbol.is_some() {
+                    symbol = sub_symbol;
+                }
+            }
+        }
+        symbol
+    }
+}
+
+impl Board {
+    /// Apply one of the 8 symmetries to every nested board, see `MetaMove::transform`
+    pub fn transform(&self, symmetry: usize) -> Board {
+        match self {
+            Board::BitBoard(bit_board) => Board::BitBoard(bit_board.transform(symmetry)),
+            Board::MetaBoard(meta_board) => Board::MetaBoard(meta_board.transform(symmetry)),
+        }
+    }
+}
+
+impl BitBoard {
+    fn transform(&self, symmetry: usize) -> BitBoard {
+        let mut transformed = BitBoard::new();
+        for i in 0..BOARD_SIZE_SQUARED {
+            transformed.set_unchecked(transform_index(i, symmetry), self.get(i));
+        }
+        transformed
+    }
+}
+
+impl MetaBoard {
+    fn transform(&self, symmetry: usize) -> MetaBoard {
+        let mut sub_boards = self.sub_boards.clone();
+        for (i, sub_board) in self.sub_boards.iter().enumerate() {
+            sub_boards[transform_index(i, symmetry)] = sub_board.transform(symmetry);
+        }
+        MetaBoard {
+            board: self.board.transform(symmetry),
+            sub_boards,
+            drawn: (0..BOARD_SIZE_SQUARED)
+                .filter(|i| self.drawn & 1 << i != 0)
+                .fold(0, |drawn, i| drawn | 1 << transform_index(i, symmetry)),
+            drawn_board_rule: self.drawn_board_rule,
+            winner: self.winner,
+        }
+    }
+}
 
 impl fmt::Display for Board {
 
@@ -621,17 +1613,121 @@ impl fmt::Display for Board {
     }
 }
 
+// #############################
+// #                           #
+// #        Evaluation         #
+// #                           #
+// #############################
+
+// Weights of the static evaluation, see `GameState::evaluate`
+const WON_SUB_BOARD_SCORE: i32 = 100;
+const OPEN_TWO_SCORE: i32 = 10;
+const CENTER_SCORE: i32 = 3;
+// Patterns on a meta board count this many times more than on its sub boards
+const META_WEIGHT: i32 = 10;
+// Score of a decided game
+pub const WIN_EVALUATION: i32 = 1_000_000;
+
+/// Static evaluation of a position for searches that stop before the end of the game
+pub trait Evaluator {
+    /// Score from the perspective of the side to move, higher is better
+    fn evaluate(&self, state: &GameState) -> i32;
+}
+
+/// The heuristic of `GameState::evaluate`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HeuristicEvaluator;
+
+impl Evaluator for HeuristicEvaluator {
+    fn evaluate(&self, state: &GameState) -> i32 {
+        state.evaluate()
+    }
+}
+
+impl Board {
+    /// Heuristic score of an undecided board for the player
+    fn evaluate(&self, player: PlayerMarker) -> i32 {
+        match self {
+            Board::BitBoard(bit_board) => bit_board.pattern_score(player),
+            Board::MetaBoard(meta_board) => meta_board.evaluate(player),
+        }
+    }
+}
+
+impl BitBoard {
+    /// Lines one marker short of a win with the rest empty and the center,
+    /// the player's minus the opponent's
+    fn pattern_score(&self, player: PlayerMarker) -> i32 {
+        let (own, other) = match player {
+            PlayerMarker::X => (self.x, self.o),
+            PlayerMarker::O => (self.o, self.x),
+            _ => return 0,
+        };
+        let open_twos = |own: Bits, other: Bits| {
+            WINNING_POSITIONS
+                .iter()
+                .filter(|&&pos| other & pos == 0 && (own & pos).count_ones() as usize == WIN_LENGTH - 1)
+                .count() as i32
+        };
+        let mut score = OPEN_TWO_SCORE * (open_twos(own, other) - open_twos(other, own));
+
+        // Only odd boards have a center field
+        if BOARD_SIZE % 2 == 1 {
+            let center = 1 << (BOARD_SIZE_SQUARED / 2);
+            if own & center != 0 {
+                score += CENTER_SCORE;
+            } else if other & center != 0 {
+                score -= CENTER_SCORE;
+            }
+        }
+        score
+    }
+
+    /// Number of won fields of the player minus those of the opponent
+    fn won_difference(&self, player: PlayerMarker) -> i32 {
+        let (own, other) = match player {
+            PlayerMarker::X => (self.x, self.o),
+            PlayerMarker::O => (self.o, self.x),
+            _ => return 0,
+        };
+        own.count_ones() as i32 - other.count_ones() as i32
+    }
+}
+
+impl MetaBoard {
+    /// Won sub boards and the patterns on this board, plus the sub boards still in play
+    fn evaluate(&self, player: PlayerMarker) -> i32 {
+        let mut score = WON_SUB_BOARD_SCORE * self.board.won_difference(player) + META_WEIGHT * self.board.pattern_score(player);
+        for sub_board in self.sub_boards.iter().filter(|sub_board| sub_board.can_set()) {
+            score += sub_board.evaluate(player);
+        }
+        score
+    }
+}
+
 // #############################
 // #                           #
 // #        GameState          #
 // #                           #
 // #############################
 
+/// Serialized as the nested cells of `Board`, the side to move, the last move and the rules
+/// 
+/// Like the notation the history is not part of it
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(into = "GameStateJson", try_from = "GameStateJson"))]
 pub struct GameState {
     pub board: Board,
     pub current_player: PlayerMarker,
     pub last_move: Option<MetaMove>,
+    /// moves played with `set`, used by `undo`
+    history: Vec<MetaMove>,
+    /// moves taken back with `undo`, used by `redo`
+    undone: Vec<MetaMove>,
+    /// Zobrist hash of the position, kept up to date by every move
+    hash: u64,
+    ruleset: Ruleset,
 }
 
 impl GameState {
@@ -640,50 +1736,2138 @@ impl GameState {
             board: Board::new(),
             current_player: PlayerMarker::X,
             last_move: None,
+            history: vec![],
+            undone: vec![],
+            hash: 0,
+            ruleset: Ruleset::default(),
+        }
+    }
+
+    /// Play by the ruleset, `Standard` by default
+    pub fn with_ruleset(mut self, ruleset: Ruleset) -> Self {
+        self.ruleset = ruleset;
+        self
+    }
+
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    /// Count drawn sub boards on their parent board as the rule says, `Neutral` by default
+    pub fn with_drawn_board_rule(mut self, rule: DrawnBoardRule) -> Self {
+        if let Board::MetaBoard(meta_board) = &mut self.board {
+            meta_board.set_drawn_board_rule(rule);
+        }
+        self
+    }
+
+    pub fn drawn_board_rule(&self) -> DrawnBoardRule {
+        match &self.board {
+            Board::MetaBoard(meta_board) => meta_board.drawn_board_rule,
+            Board::BitBoard(_) => DrawnBoardRule::default(),
         }
     }
 
+    /// Empty board on which the given player makes the first move
+    pub fn with_starting_player(player: PlayerMarker) -> Self {
+        let mut state = GameState::new();
+        state.current_player = player;
+        state.hash = state.compute_zobrist_hash();
+        state
+    }
+
     pub fn get_winner(&self) -> PlayerMarker {
         self.board.get_winner()
     }
 
-    pub fn set(&mut self, meta_move: MetaMove) -> Result<PlayerMarker, InvalidMoveError> {
+    /// Result of the game in words: `X wins`, `O wins`, `Draw` or `In progress`
+    pub fn winner_or_draw_text(&self) -> &'static str {
+        match self.outcome() {
+            Some(GameOutcome::Win(PlayerMarker::X)) => "X wins",
+            Some(GameOutcome::Win(_)) => "O wins",
+            Some(GameOutcome::Draw) => "Draw",
+            None => "In progress",
+        }
+    }
 
-        match self.board.set(meta_move.absolute_index.as_slice(), self.current_player){
-            Ok(marker) => {
-                self.current_player = self.current_player.to_other();
-                self.last_move = Some(meta_move);
-                return Ok(marker);
-            }
-            Err(e) => return Err(e),
+    /// Result of the game, `None` while play continues
+    /// 
+    /// Without a winner the game is drawn once no cell can be set anymore
+    /// or no player can complete a meta line (see `is_forced_draw`)
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        match self.get_winner() {
+            winner @ (PlayerMarker::X | PlayerMarker::O) => Some(GameOutcome::Win(winner)),
+            PlayerMarker::Draw => Some(GameOutcome::Draw),
+            PlayerMarker::Empty if !self.board.can_set() || self.is_forced_draw() => Some(GameOutcome::Draw),
+            PlayerMarker::Empty => None,
+        }
+    }
+
+    /// Bitmask over `WINNING_POSITIONS` of the meta lines the player can still complete
+    /// 
+    /// A line is live as long as the opponent hasn't won a sub board on it,
+    /// drawn or dead sub boards are not taken into account (see `is_forced_draw`)
+    pub fn live_meta_lines(&self, player: PlayerMarker) -> u32 {
+        match &self.board {
+            Board::BitBoard(bit_board) => bit_board.live_lines(player),
+            Board::MetaBoard(meta_board) => meta_board.board.live_lines(player),
+        }
+    }
+
+    /// Number of meta lines X and O can still complete, see `live_meta_lines`
+    pub fn line_pressure(&self) -> (u8, u8) {
+        (
+            self.live_meta_lines(PlayerMarker::X).count_ones() as u8,
+            self.live_meta_lines(PlayerMarker::O).count_ones() as u8,
+        )
+    }
+
+    /// Static score of the position from the perspective of the side to move
+    /// 
+    /// Counts won sub boards, lines one marker short of a win with the rest empty
+    /// and the center fields, on the meta board ten times as much as on a sub board.
+    /// A decided game scores `WIN_EVALUATION`, `-WIN_EVALUATION` or 0.
+    pub fn evaluate(&self) -> i32 {
+        match self.outcome() {
+            Some(GameOutcome::Win(winner)) if winner == self.current_player => WIN_EVALUATION,
+            Some(GameOutcome::Win(_)) => -WIN_EVALUATION,
+            Some(GameOutcome::Draw) => 0,
+            None if self.board.can_set() => self.board.evaluate(self.current_player),
+            None => 0,
         }
     }
 
+    /// Check if the game is a draw even though there are moves left
+    /// 
+    /// This is the case when every meta line passes through a sub board that is dead
+    /// or won by the other player
+    pub fn is_forced_draw(&self) -> bool {
+        self.is_drawn_by_exhaustion() || (self.get_winner() == PlayerMarker::Empty && self.board.is_dead())
+    }
+
+    /// Check if every meta line holds sub boards won by both players
+    /// 
+    /// Only looks at the won sub boards, so it is cheaper than `is_forced_draw`
+    /// but misses draws caused by drawn or dead sub boards
+    pub fn is_drawn_by_exhaustion(&self) -> bool {
+        self.get_winner() == PlayerMarker::Empty
+            && self.live_meta_lines(PlayerMarker::X) == 0
+            && self.live_meta_lines(PlayerMarker::O) == 0
+    }
+
+    pub fn set(&mut self, meta_move: MetaMove) -> Result<PlayerMarker, InvalidMove> {
+        let marker = self.make_move(meta_move)?;
+        self.undone.clear();
+        Ok(marker)
+    }
+
+    /// Play a move and push it on the move stack, `unmake_move` takes it back
+    /// 
+    /// Unlike `set` the moves taken back with `undo` are kept
+    pub fn make_move(&mut self, meta_move: MetaMove) -> Result<PlayerMarker, InvalidMove> {
+        let marker = self.board.set(meta_move.absolute_index.as_slice(), self.current_player)?;
+        self.hash ^= self.move_keys(meta_move, self.current_player, self.last_move);
+        self.current_player = self.current_player.to_other();
+        self.last_move = Some(meta_move);
+        self.history.push(meta_move);
+        Ok(marker)
+    }
+
+    /// Same as `make_move` without validating the move, see `set_unchecked`
+    pub fn make_move_unchecked(&mut self, meta_move: MetaMove) -> PlayerMarker {
+        let marker = self.set_unchecked(meta_move);
+        self.history.push(meta_move);
+        marker
+    }
+
+    /// Take back the last move of the move stack
+    /// 
+    /// Restores the player and the last move and clears sub board wins the move caused
+    pub fn unmake_move(&mut self) -> Option<MetaMove> {
+        let meta_move = self.history.pop()?;
+        self.unset(self.history.last().copied());
+        Some(meta_move)
+    }
+
+    /// Set a move without validating it
+    ///
+    /// Skips the occupancy checks and the error path of `set`. This can't cause UB,
+    /// but an illegal move silently corrupts the game state, so only pass moves
+    /// that come straight from `get_possible_moves` (e.g. inside the search).
+    /// The move is not recorded in the history.
+    pub fn set_unchecked(&mut self, meta_move: MetaMove) -> PlayerMarker {
+        let marker = self.board.set_unchecked(meta_move.absolute_index.as_slice(), self.current_player);
+        self.hash ^= self.move_keys(meta_move, self.current_player, self.last_move);
+        self.current_player = self.current_player.to_other();
+        self.last_move = Some(meta_move);
+        marker
+    }
+
+    /// Take back the last move without touching the history
+    ///
+    /// Counterpart of `set_unchecked` for the search, use `undo` otherwise
     pub fn unset(&mut self, previous_move: Option<MetaMove>) {
-        if let Some(last_move) = &self.last_move {
+        if let Some(last_move) = self.last_move {
             self.board.unset(last_move.absolute_index.as_slice());
             self.current_player = self.current_player.to_other();
+            self.hash ^= self.move_keys(last_move, self.current_player, previous_move);
             self.last_move = previous_move;
         }
     }
 
+    /// Hash of the cells, the side to move and the board the last move sends to
+    /// 
+    /// Move orders that reach the same position have the same hash. It is updated
+    /// incrementally with every move and taken back move.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Hash of the position after the move, without playing it
+    pub fn zobrist_hash_after(&self, meta_move: MetaMove) -> u64 {
+        self.hash ^ self.move_keys(meta_move, self.current_player, self.last_move)
+    }
+
+    /// Hash of the position computed from scratch, see `zobrist_hash`
+    fn compute_zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for (cell_index, (_, marker)) in self.cells().enumerate() {
+            match marker {
+                PlayerMarker::X => hash ^= ZOBRIST_CELL_KEYS[2 * cell_index],
+                PlayerMarker::O => hash ^= ZOBRIST_CELL_KEYS[2 * cell_index + 1],
+                _ => {}
+            }
+        }
+        if self.current_player == PlayerMarker::O {
+            hash ^= ZOBRIST_SIDE_KEY;
+        }
+        hash ^ self.last_move.map_or(0, forced_key)
+    }
+
+    /// Keys that change when the player plays the move after `previous_move`
+    fn move_keys(&self, meta_move: MetaMove, player: PlayerMarker, previous_move: Option<MetaMove>) -> u64 {
+        let cell_key = ZOBRIST_CELL_KEYS[2 * meta_move.cell_index() + (player == PlayerMarker::O) as usize];
+        cell_key ^ ZOBRIST_SIDE_KEY ^ previous_move.map_or(0, forced_key) ^ forced_key(meta_move)
+    }
+
+    /// Number of moves on the board, moves taken back with `unmake_move` or `undo` don't count
+    pub fn ply(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Take back the last move played with `set`
+    ///
+    /// Returns the move that was taken back
+    pub fn undo(&mut self) -> Option<MetaMove> {
+        let meta_move = self.unmake_move()?;
+        self.undone.push(meta_move);
+        Some(meta_move)
+    }
+
+    /// Replay the last move taken back with `undo`
+    ///
+    /// Returns the move that was replayed
+    pub fn redo(&mut self) -> Option<MetaMove> {
+        let meta_move = self.undone.pop()?;
+        self.make_move_unchecked(meta_move);
+        Some(meta_move)
+    }
+
+    /// Number of move sequences of `depth` plies from this position, games that end earlier are not counted
+    /// 
+    /// Walks the whole game tree, so it is only meant to validate the move generation
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let possible_moves = self.legal_moves();
+        if depth == 1 {
+            return possible_moves.len() as u64;
+        }
+        let mut count = 0;
+        for &meta_move in &possible_moves {
+            self.make_move(meta_move).expect("generated an illegal move");
+            count += self.perft(depth - 1);
+            self.unmake_move();
+        }
+        count
+    }
+
+    /// Rewind the game to the state after the nth move
+    pub fn undo_to_ply(&mut self, n: usize) {
+        while self.ply() > n {
+            self.undo();
+        }
+    }
+
     pub fn get_possible_moves(&self, possible_moves: &mut PossibleMoves, next_move: &mut MetaMove) {
         
-        let mut next_index: &[usize] = &[];
-        let temp;
-        if let Some(last_move) = &self.last_move {
-            temp = last_move.shift_left();
-            next_index = temp.absolute_index.as_slice();
-        }
+        let forced_move = self.forced_move();
+        let next_index = forced_move.as_ref().map_or(&[][..], |forced_move| forced_move.absolute_index.as_slice());
         
         possible_moves.clear();
         next_move.clear();
         self.board.get_empty_positions(next_index, possible_moves, next_move);
     }
-}
 
-impl fmt::Display for GameState {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.board.fmt(f)
+    /// Check if the move would win its (innermost) sub board for the player
+    pub fn wins_sub_board(&self, meta_move: MetaMove, player: PlayerMarker) -> bool {
+        let index = meta_move.absolute_index;
+        self.board
+            .get_bit_board(&index)
+            .is_winning_move(index[META_DEPTH - 1], player)
+    }
+
+    /// Check if the move would win the game for the player, without playing it
+    /// 
+    /// Drawn sub boards count as the `DrawnBoardRule` says
+    pub fn wins_game(&self, meta_move: MetaMove, player: PlayerMarker) -> bool {
+        self.board.winner_after(&meta_move.absolute_index, player) == player
+    }
+
+    /// Iterate over the top level sub boards with their index
+    pub fn iter_sub_boards(&self) -> impl Iterator<Item = (usize, &Board)> {
+        self.board.sub_boards().iter().enumerate()
+    }
+
+    /// Index and winner of every decided top level sub board, drawn boards included
+    pub fn won_sub_boards(&self) -> Vec<(usize, PlayerMarker)> {
+        self.iter_sub_boards()
+            .map(|(i, board)| (i, board.get_winner()))
+            .filter(|&(_, winner)| winner != PlayerMarker::Empty)
+            .collect()
+    }
+
+    /// Index paths of the innermost boards the current player may play in
+    /// 
+    /// The board the last move sends to, or every open board if that one is decided
+    /// or full. Boards inside decided boards are left out, no board once the game is over.
+    pub fn active_boards(&self) -> Vec<[usize; META_DEPTH - 1]> {
+        let mut boards: Vec<[usize; META_DEPTH - 1]> = self.legal_moves()
+            .into_iter()
+            .map(|meta_move| meta_move.board().try_into().unwrap())
+            .collect();
+        // The moves of a board are listed together
+        boards.dedup();
+        boards
+    }
+
+    /// Every cell with its marker, in `MetaMove::cell_index` order
+    pub fn cells(&self) -> impl Iterator<Item = (MetaMove, PlayerMarker)> + '_ {
+        (0..META_SIZE).map(|cell_index| {
+            let meta_move = MetaMove::from_cell_index(cell_index);
+            (meta_move, self.board.get(&meta_move.absolute_index).unwrap_or(PlayerMarker::Empty))
+        })
+    }
+
+    /// Marker of the cell at the index path, the outermost board first
+    /// 
+    /// The path needs `META_DEPTH` indices in `0..BOARD_SIZE_SQUARED`, like `MetaMove::absolute_index`
+    pub fn cell(&self, path: &[usize]) -> Result<PlayerMarker, InvalidMove> {
+        if path.len() < META_DEPTH {
+            return Err(InvalidMove::IndexTooShort);
+        }
+        if path.len() > META_DEPTH || path.iter().any(|&index| index >= BOARD_SIZE_SQUARED) {
+            return Err(InvalidMove::OutOfBounds);
+        }
+        self.board.get(path)
+    }
+
+    /// Winner of the board at the index path, `Draw` if it is full without a winner
+    /// 
+    /// The empty path is the whole game. Paths of cells or out of range give `Empty`.
+    pub fn sub_board_winner(&self, path: &[usize]) -> PlayerMarker {
+        let mut board = &self.board;
+        for &index in path {
+            match board {
+                Board::MetaBoard(meta_board) if index < BOARD_SIZE_SQUARED => board = &meta_board.sub_boards[index],
+                _ => return PlayerMarker::Empty,
+            }
+        }
+        board.get_winner()
+    }
+
+    /// Score every cell for the current player, indexed by `MetaMove::cell_index`
+    /// 
+    /// Occupied cells and cells in decided boards score 0,
+    /// see `BitBoard::cell_score` for the other values
+    pub fn threat_map(&self) -> [f32; META_SIZE] {
+        let mut map = [0.; META_SIZE];
+        self.board.fill_threat_map(self.current_player, &mut map);
+        map
+    }
+
+    /// Key of the position for storing and looking up opening books
+    /// 
+    /// The key is the same for all symmetric positions and starts with `BOOK_KEY_VERSION`,
+    /// so books with an outdated key format can be detected
+    pub fn key_for_book(&self) -> String {
+        self.book_key(BOOK_KEY_VERSION)
+    }
+
+    fn book_key(&self, version: u8) -> String {
+        (0..8)
+            .map(|symmetry| self.packed_key(version, symmetry))
+            .min()
+            .unwrap()
+    }
+
+    /// Short stable string of the position for logs, not meant to be read
+    /// 
+    /// Base64 of the side to move, the board the last move sends to and 2 bits per cell.
+    /// Move orders that reach the same position give the same fingerprint.
+    pub fn fingerprint(&self) -> String {
+        const _: () = assert!(2 * INNER_BOARDS < u16::MAX as usize);
+        let side = if self.current_player == PlayerMarker::X { 0 } else { 1 };
+        let header = (self.last_move.map_or(INNER_BOARDS, forced_board) * 2 + side) as u16;
+
+        let mut bytes = header.to_be_bytes().to_vec();
+        let cells: Vec<_> = self.cells().collect();
+        for chunk in cells.chunks(4) {
+            bytes.push(chunk.iter().fold(0, |acc, &(_, marker)| acc << 2 | match marker {
+                PlayerMarker::X => 1,
+                PlayerMarker::O => 2,
+                _ => 0,
+            }));
+        }
+        base64_encode(&bytes)
+    }
+
+    /// Hex encoded version, side to move, last move and 2 bits per cell of the transformed position
+    fn packed_key(&self, version: u8, symmetry: usize) -> String {
+        let mut cells = [0u8; META_SIZE];
+        for (meta_move, marker) in self.cells() {
+            cells[meta_move.transform(symmetry).cell_index()] = match marker {
+                PlayerMarker::X => 1,
+                PlayerMarker::O => 2,
+                _ => 0,
+            };
+        }
+
+        let side = if self.current_player == PlayerMarker::X { 0 } else { 1 };
+        let last_move = self.last_move.map_or(u16::MAX, |m| m.transform(symmetry).cell_index() as u16);
+        let mut key = format!("{:02x}{:02x}{:04x}", version, side, last_move);
+        for chunk in cells.chunks(4) {
+            key.push_str(&format!("{:02x}", chunk.iter().fold(0, |acc, &cell| acc << 2 | cell)));
+        }
+        key
+    }
+
+    /// The same position under one of the 8 symmetries, see `MetaMove::transform`
+    pub fn transform(&self, symmetry: usize) -> GameState {
+        let transformed = GameState {
+            board: self.board.transform(symmetry),
+            current_player: self.current_player,
+            last_move: self.last_move.map(|meta_move| meta_move.transform(symmetry)),
+            history: self.history.iter().map(|meta_move| meta_move.transform(symmetry)).collect(),
+            undone: self.undone.iter().map(|meta_move| meta_move.transform(symmetry)).collect(),
+            hash: 0,
+            ruleset: self.ruleset,
+        };
+        GameState { hash: transformed.compute_zobrist_hash(), ..transformed }
+    }
+
+    /// Symmetries that map the position onto itself, starting with the identity
+    /// 
+    /// Besides the cells the board the last move sends to has to stay in place,
+    /// otherwise symmetric moves wouldn't be legal alike
+    fn symmetries(&self) -> Vec<usize> {
+        iter::once(0)
+            .chain((1..8).filter(|&symmetry| {
+                let forced_board = |forced_move: MetaMove| forced_move.board().to_vec();
+                self.forced_move().map(|meta_move| forced_board(meta_move.transform(symmetry))) == self.forced_move().map(forced_board)
+                    && self.board.transform(symmetry) == self.board
+            }))
+            .collect()
     }
+
+    /// The legal moves with symmetric moves reduced to their `MetaMove::canonical` one
+    /// 
+    /// Symmetric moves lead to equal positions, so a search only has to look at one of them
+    pub fn canonical_moves(&self, out: &mut PossibleMoves) {
+        let symmetries = self.symmetries();
+        self.get_possible_moves(out, &mut MetaMove::new_empty());
+        if symmetries.len() > 1 {
+            out.retain(|&meta_move| canonical_under(meta_move, &symmetries).cell_index() == meta_move.cell_index());
+        }
+    }
+
+    /// All 8 symmetric copies of a training sample, starting with the identity
+    /// 
+    /// The policy is indexed by `MetaMove::cell_index` and is remapped with the board,
+    /// the value doesn't change under symmetry
+    pub fn flip_and_augment(&self, policy: &[f32; META_SIZE], value: f32) -> Vec<(GameState, [f32; META_SIZE], f32)> {
+        (0..8)
+            .map(|symmetry| {
+                let mut transformed_policy = [0.; META_SIZE];
+                for (cell_index, &probability) in policy.iter().enumerate() {
+                    let meta_move = MetaMove::from_cell_index(cell_index).transform(symmetry);
+                    transformed_policy[meta_move.cell_index()] = probability;
+                }
+                (self.transform(symmetry), transformed_policy, value)
+            })
+            .collect()
+    }
+
+    /// Get all legal moves for the current player
+    ///
+    /// Allocates its own buffers, use `get_possible_moves` in hot paths
+    pub fn legal_moves(&self) -> PossibleMoves {
+        let mut possible_moves = PossibleMoves::new();
+        self.get_possible_moves(&mut possible_moves, &mut MetaMove::new_empty());
+        possible_moves
+    }
+
+    /// Index path that starts with the board the next move has to go to, `None` for any board
+    /// 
+    /// Usually the last move shifted left, so its cell becomes the board, see `Ruleset`
+    fn forced_move(&self) -> Option<MetaMove> {
+        let last_move = self.last_move?;
+        match self.ruleset {
+            Ruleset::Free => None,
+            Ruleset::SendToClaimed if matches!(self.sub_board_winner(last_move.board()), PlayerMarker::X | PlayerMarker::O) => Some(last_move),
+            _ => Some(last_move.shift_left()),
+        }
+    }
+
+    /// Iterate over the legal moves without managing any buffers
+    ///
+    /// Collects the moves once up front, use `get_possible_moves` in hot paths
+    pub fn moves(&self) -> impl Iterator<Item = MetaMove> + '_ {
+        let possible_moves = self.legal_moves();
+        (0..possible_moves.len()).map(move |i| possible_moves[i])
+    }
+
+    /// Check if the move can be played, without collecting all legal moves
+    /// 
+    /// The move has to go to the board the last move sends to, unless that board
+    /// is decided or full. The cell has to be empty and no enclosing board decided.
+    pub fn is_legal(&self, meta_move: MetaMove) -> bool {
+        self.check_move(meta_move).is_ok()
+    }
+
+    /// Same as `is_legal`, with the reason the move can't be played for feedback to the user
+    pub fn check_move(&self, meta_move: MetaMove) -> Result<(), InvalidMove> {
+        match self.forced_move() {
+            Some(forced_move) => self.board.check_move(&meta_move.absolute_index, &forced_move.absolute_index),
+            None => self.board.check_move(&meta_move.absolute_index, &[]),
+        }
+    }
+
+    /// Replay a whitespace separated list of moves like `4.4 4.0` from the start position
+    /// 
+    /// Every move has to be legal, including the board the previous move sends to
+    pub fn from_move_list(moves: &str) -> Result<Self, InvalidMove> {
+        let mut state = GameState::new();
+        for part in moves.split_whitespace() {
+            let meta_move: MetaMove = part.parse()?;
+            state.check_move(meta_move)?;
+            state.set(meta_move)?;
+        }
+        Ok(state)
+    }
+
+    /// Compact text form of the position, read back by `from_notation`
+    /// 
+    /// Every cell in cell index order as `X`, `O` or `_` with a `/` after each innermost
+    /// board, then the side to move and the last move or `-`, e.g. `____X____/_________/... O 4.4`.
+    /// The history is not part of the notation.
+    pub fn to_notation(&self) -> String {
+        let mut notation = String::with_capacity(META_SIZE + INNER_BOARDS + 8);
+        for (cell_index, (_, marker)) in self.cells().enumerate() {
+            notation.push(marker.to_char());
+            if cell_index % BOARD_SIZE_SQUARED == BOARD_SIZE_SQUARED - 1 && cell_index + 1 < META_SIZE {
+                notation.push('/');
+            }
+        }
+        notation.push(' ');
+        notation.push(self.current_player.to_char());
+        notation.push(' ');
+        match self.last_move {
+            Some(last_move) => notation.push_str(&last_move.to_string()),
+            None => notation.push('-'),
+        }
+        notation
+    }
+
+    /// Read a position written by `to_notation`
+    /// 
+    /// The last move has to be a cell of the player who is not to move
+    pub fn from_notation(s: &str) -> Result<Self, InvalidMove> {
+        let invalid = |reason: &str| InvalidMove::Malformed(format!("Invalid notation '{}': {}", s, reason));
+
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let [cells, player, last_move] = parts[..] else {
+            return Err(invalid("expected cells, side to move and last move"));
+        };
+
+        let mut state = GameState::new();
+        let boards: Vec<&str> = cells.split('/').collect();
+        if boards.len() != INNER_BOARDS || boards.iter().any(|board| board.chars().count() != BOARD_SIZE_SQUARED) {
+            return Err(invalid("wrong number of cells"));
+        }
+        for (cell_index, cell) in boards.concat().chars().enumerate() {
+            let marker = match cell {
+                'X' => PlayerMarker::X,
+                'O' => PlayerMarker::O,
+                '_' => continue,
+                _ => return Err(invalid("unknown cell")),
+            };
+            state.board.set_unchecked(&MetaMove::from_cell_index(cell_index).absolute_index, marker);
+        }
+        // The cells are set in index order instead of the order they were played in,
+        // decide the boards from the finished cells
+        if let Board::MetaBoard(meta_board) = &mut state.board {
+            meta_board.update_won_boards();
+        }
+
+        state.current_player = match player {
+            "X" => PlayerMarker::X,
+            "O" => PlayerMarker::O,
+            _ => return Err(invalid("unknown side to move")),
+        };
+        if last_move != "-" {
+            let last_move: MetaMove = last_move.parse()?;
+            if state.board.get(&last_move.absolute_index)? != state.current_player.to_other() {
+                return Err(invalid("the last move is not a cell of the other player"));
+            }
+            state.last_move = Some(last_move);
+        }
+        state.hash = state.compute_zobrist_hash();
+        Ok(state)
+    }
+}
+
+// #############################
+// #                           #
+// #           Serde           #
+// #                           #
+// #############################
+
+/// Index path of the move, the outermost board first
+#[cfg(feature = "serde")]
+impl serde::Serialize for MetaMove {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.absolute_index.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MetaMove {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let index = Vec::<usize>::deserialize(deserializer)?;
+        MetaMove::try_new(&index).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Nested arrays, a bit board is the array of its cell markers and a meta board the array of its sub boards
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Board::BitBoard(bit_board) => serializer.collect_seq((0..BOARD_SIZE_SQUARED).map(|i| bit_board.get(i))),
+            Board::MetaBoard(meta_board) => serializer.collect_seq(meta_board.sub_boards.iter()),
+        }
+    }
+}
+
+/// Decided boards follow from the cells, drawn boards count as `DrawnBoardRule::Neutral`
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(untagged)]
+        enum BoardJson {
+            Cells(Vec<PlayerMarker>),
+            Boards(Vec<Board>),
+        }
+
+        let invalid = |reason: &str| serde::de::Error::custom(format!("Invalid board: {}", reason));
+        match BoardJson::deserialize(deserializer)? {
+            BoardJson::Cells(cells) => {
+                if cells.len() != BOARD_SIZE_SQUARED {
+                    return Err(invalid("wrong number of cells"));
+                }
+                let mut bit_board = BitBoard::new();
+                for (i, marker) in cells.into_iter().enumerate() {
+                    match marker {
+                        PlayerMarker::X | PlayerMarker::O => {
+                            bit_board.set_unchecked(i, marker);
+                        }
+                        PlayerMarker::Empty => {}
+                        PlayerMarker::Draw => return Err(invalid("a cell can't be a draw")),
+                    }
+                }
+                Ok(Board::BitBoard(bit_board))
+            }
+            BoardJson::Boards(sub_boards) => {
+                let depth = sub_boards.first().map(Board::depth);
+                if sub_boards.iter().any(|sub_board| Some(sub_board.depth()) != depth) {
+                    return Err(invalid("sub boards of different depth"));
+                }
+                let sub_boards: Box<[Board; BOARD_SIZE_SQUARED]> = sub_boards
+                    .into_boxed_slice()
+                    .try_into()
+                    .map_err(|_| invalid("wrong number of sub boards"))?;
+                let mut meta_board = MetaBoard {
+                    board: BitBoard::new(),
+                    sub_boards,
+                    drawn: 0,
+                    drawn_board_rule: DrawnBoardRule::default(),
+                    winner: PlayerMarker::Empty,
+                };
+                meta_board.update_won_boards();
+                Ok(Board::MetaBoard(meta_board))
+            }
+        }
+    }
+}
+
+/// Stable JSON shape of `GameState`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameStateJson {
+    board: Board,
+    current_player: PlayerMarker,
+    last_move: Option<MetaMove>,
+    ruleset: Ruleset,
+    drawn_board_rule: DrawnBoardRule,
+}
+
+#[cfg(feature = "serde")]
+impl From<GameState> for GameStateJson {
+    fn from(state: GameState) -> Self {
+        GameStateJson {
+            current_player: state.current_player,
+            last_move: state.last_move,
+            ruleset: state.ruleset,
+            drawn_board_rule: state.drawn_board_rule(),
+            board: state.board,
+        }
+    }
+}
+
+/// Same checks as `GameState::from_notation`
+#[cfg(feature = "serde")]
+impl TryFrom<GameStateJson> for GameState {
+    type Error = InvalidMove;
+
+    fn try_from(json: GameStateJson) -> Result<Self, Self::Error> {
+        let invalid = |reason: &str| InvalidMove::Malformed(format!("Invalid game state: {}", reason));
+        if json.board.depth() != META_DEPTH {
+            return Err(invalid("wrong board depth"));
+        }
+        if !matches!(json.current_player, PlayerMarker::X | PlayerMarker::O) {
+            return Err(invalid("unknown side to move"));
+        }
+
+        let mut state = GameState { board: json.board, current_player: json.current_player, ..GameState::new() }
+            .with_ruleset(json.ruleset)
+            .with_drawn_board_rule(json.drawn_board_rule);
+        if let Some(last_move) = json.last_move {
+            if state.board.get(&last_move.absolute_index)? != state.current_player.to_other() {
+                return Err(invalid("the last move is not a cell of the other player"));
+            }
+            state.last_move = Some(last_move);
+        }
+        state.hash = state.compute_zobrist_hash();
+        Ok(state)
+    }
+}
+
+/// Key of the innermost board the move sends the next player to
+fn forced_key(meta_move: MetaMove) -> u64 {
+    ZOBRIST_FORCED_KEYS[forced_board(meta_move)]
+}
+
+/// Number of the innermost board the move sends the next player to, below `INNER_BOARDS`
+fn forced_board(meta_move: MetaMove) -> usize {
+    meta_move.absolute_index[1..]
+        .iter()
+        .fold(0, |acc, &index| acc * BOARD_SIZE_SQUARED + index)
+}
+
+/// URL safe base64 without padding
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |acc, (i, &byte)| acc | (byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+/// Two game states are equal if they describe the same position, the history is ignored
+impl PartialEq for GameState {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.current_player == other.current_player
+            && self.ruleset == other.ruleset
+            // Parsed and generated moves of the same cell differ in the private index
+            && self.last_move.map(|m| m.absolute_index) == other.last_move.map(|m| m.absolute_index)
+    }
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState::new()
+    }
+}
+
+impl GameState {
+    /// Stream the board to a writer, the output matches `Display`
+    pub fn render(&self, w: &mut impl io::Write) -> io::Result<()> {
+        self.board.render(w)
+    }
+
+    /// Stream the board to a writer drawn with the options, see `Board::render_with`
+    pub fn render_with(&self, w: &mut impl io::Write, options: DisplayOptions) -> io::Result<()> {
+        self.board.render_with(w, options)
+    }
+
+    /// The `Display` output with the last move and the boards of the next move colored
+    /// 
+    /// If the last move sends to a decided board, every open board is highlighted.
+    /// Decided boards get the background of their winner, white for a draw.
+    pub fn render_highlighted(&self) -> String {
+        self.render_painted(|symbol, highlight| match highlight {
+            Highlight::LastMove => symbol.black().on_yellow().to_string(),
+            Highlight::Decided(PlayerMarker::X) => symbol.white().on_red().to_string(),
+            Highlight::Decided(PlayerMarker::O) => symbol.white().on_blue().to_string(),
+            Highlight::Decided(_) => symbol.black().on_white().to_string(),
+            Highlight::Active => symbol.on_bright_black().to_string(),
+        })
+    }
+
+    /// The `Display` output with every highlighted cell passed through `paint`
+    pub fn render_painted(&self, paint: impl Fn(&str, Highlight) -> String) -> String {
+        let last_move = self.last_move.map(|meta_move| display_region(&meta_move.absolute_index));
+        let active_boards: Vec<_> = self.active_boards().iter().map(|board| display_region(board)).collect();
+        // Outer boards first, a decided board hides the boards inside it
+        let decided_boards: Vec<_> = (1..META_DEPTH)
+            .flat_map(|length| (0..BOARD_SIZE_SQUARED.pow(length as u32)).map(move |board| {
+                // The last indices of a cell index path are the path of a board
+                let index = MetaMove::from_cell_index(board).absolute_index;
+                let path = &index[META_DEPTH - length..];
+                (display_region(path), self.sub_board_winner(path))
+            }))
+            .filter(|&(_, winner)| winner != PlayerMarker::Empty)
+            .collect();
+
+        let contains = |(top, left, width): (usize, usize, usize), (row, col): (usize, usize)| {
+            (top..top + width).contains(&row) && (left..left + width).contains(&col)
+        };
+        let mut rendered = String::new();
+        for row in 0..DISPLAY_SIZE {
+            for col in 0..DISPLAY_SIZE {
+                let symbol = self.board.display_char((row, col), META_DEPTH, DISPLAY_SIZE).unwrap_or(' ').to_string();
+                let decided = decided_boards.iter().find(|&&(region, _)| contains(region, (row, col)));
+                if last_move.is_some_and(|region| contains(region, (row, col))) {
+                    rendered.push_str(&paint(&symbol, Highlight::LastMove));
+                } else if let Some(&(_, winner)) = decided {
+                    rendered.push_str(&paint(&symbol, Highlight::Decided(winner)));
+                } else if active_boards.iter().any(|&region| contains(region, (row, col))) {
+                    rendered.push_str(&paint(&symbol, Highlight::Active));
+                } else {
+                    rendered.push_str(&symbol);
+                }
+            }
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+/// Why a cell of `GameState::render_painted` is highlighted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Highlight {
+    /// Part of the last move
+    LastMove,
+    /// On a board decided for the player, `Draw` for a full board without a winner
+    Decided(PlayerMarker),
+    /// On a board of the next move
+    Active,
+}
+
+/// Top row, left column and width of the board or cell at the index path in the `Display` output
+fn display_region(path: &[usize]) -> (usize, usize, usize) {
+    let (mut top, mut left, mut width) = (0, 0, DISPLAY_SIZE);
+    for (level, &index) in path.iter().enumerate() {
+        let (sub_width, gap) = sub_board_layout(META_DEPTH - level, width);
+        top += index / BOARD_SIZE * (sub_width + gap);
+        left += index % BOARD_SIZE * (sub_width + gap);
+        width = sub_width;
+    }
+    (top, left, width)
+}
+
+impl fmt::Display for GameState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.board.fmt(f)
+    }
+}
+
+// #############################
+// #                           #
+// #           Tests           #
+// #                           #
+// #############################
+
+#[test]
+fn test_game_state_default() {
+    assert!(GameState::default() == GameState::new());
+}
+
+#[test]
+fn test_legal_moves_first_move() {
+    // Without a last move every cell of every sub board is legal
+    let moves = GameState::new().legal_moves();
+    assert_eq!(moves.len(), META_SIZE);
+
+    let mut seen = [false; META_SIZE];
+    for move_ in moves.into_iter() {
+        let cell = move_.absolute_index
+            .iter()
+            .fold(0, |acc, &index| acc * BOARD_SIZE_SQUARED + index);
+        assert!(!seen[cell], "duplicate move {:?}", move_.absolute_index);
+        seen[cell] = true;
+    }
+    assert!(seen.iter().all(|&cell| cell));
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_cell() {
+    let mut state = GameState::from_move_list("4.4 4.0").unwrap();
+    assert_eq!(state.cell(&[4, 4]), Ok(PlayerMarker::X));
+    assert_eq!(state.cell(&[4, 0]), Ok(PlayerMarker::O));
+    assert_eq!(state.cell(&[0, 4]), Ok(PlayerMarker::Empty));
+
+    assert!(state.cell(&[]).is_err());
+    assert!(state.cell(&[4]).is_err());
+    assert!(state.cell(&[4, 4, 0]).is_err());
+    assert!(state.cell(&[4, BOARD_SIZE_SQUARED]).is_err());
+    assert!(state.cell(&[BOARD_SIZE_SQUARED, 0]).is_err());
+
+    assert_eq!(state.sub_board_winner(&[0]), PlayerMarker::Empty);
+    for index in [[0, 0], [8, 8], [0, 1], [8, 7], [0, 2]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(state.sub_board_winner(&[0]), PlayerMarker::X);
+    assert_eq!(state.sub_board_winner(&[8]), PlayerMarker::Empty);
+    assert_eq!(state.sub_board_winner(&[]), PlayerMarker::Empty);
+    // Cells and invalid paths are no boards
+    assert_eq!(state.sub_board_winner(&[0, 0]), PlayerMarker::Empty);
+    assert_eq!(state.sub_board_winner(&[BOARD_SIZE_SQUARED]), PlayerMarker::Empty);
+
+    // A full board without a line is a draw
+    let drawn = BitBoard::from_bits(0b010_001_101, 0b101_110_010).unwrap();
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    meta_board.sub_boards[2] = Board::BitBoard(drawn);
+    assert_eq!(state.sub_board_winner(&[2]), PlayerMarker::Draw);
+}
+
+#[test]
+fn test_cells() {
+    let state = GameState::new();
+    assert_eq!(state.cells().count(), BOARD_SIZE_SQUARED.pow(META_DEPTH as u32));
+    assert!(state.cells().all(|(_, marker)| marker == PlayerMarker::Empty));
+
+    let state = GameState::from_move_list("4.4 4.0").unwrap();
+    let taken: Vec<_> = state.cells()
+        .filter(|&(_, marker)| marker != PlayerMarker::Empty)
+        .map(|(meta_move, marker)| (meta_move.to_string(), marker))
+        .collect();
+    assert_eq!(taken, [("4.0".to_string(), PlayerMarker::O), ("4.4".to_string(), PlayerMarker::X)]);
+    for (cell_index, (meta_move, marker)) in state.cells().enumerate() {
+        assert_eq!(meta_move.cell_index(), cell_index);
+        assert_eq!(state.cell(&meta_move.absolute_index), Ok(marker));
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_fingerprint() {
+    let state = GameState::from_move_list("4.4 4.0 0.4").unwrap();
+    let fingerprint = state.fingerprint();
+    assert_eq!(fingerprint.len(), (2 + META_SIZE.div_ceil(4)).div_ceil(3) * 4 - 1);
+    assert!(fingerprint.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+    // Equal positions, also reached by another move order
+    assert_eq!(state.clone().fingerprint(), fingerprint);
+    assert_eq!(GameState::from_notation(&state.to_notation()).unwrap().fingerprint(), fingerprint);
+    // Both last moves send to board 4
+    let mut transposed = GameState::new();
+    for index in [[0, 4], [4, 0], [4, 4]] {
+        transposed.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(transposed.fingerprint(), fingerprint);
+
+    // One move more or a different move
+    let mut next = state.clone();
+    next.set(MetaMove::new(&[4, 1])).unwrap();
+    assert_ne!(next.fingerprint(), fingerprint);
+    assert_ne!(GameState::from_move_list("4.4 4.0 0.5").unwrap().fingerprint(), fingerprint);
+    assert_ne!(GameState::new().fingerprint(), fingerprint);
+
+    assert_eq!(base64_encode(b"Man"), "TWFu");
+    assert_eq!(base64_encode(b"Ma"), "TWE");
+    assert_eq!(base64_encode(&[0xfb, 0xff]), "-_8");
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_active_boards() {
+    assert_eq!(GameState::new().active_boards().len(), INNER_BOARDS);
+
+    // Sent to board 0
+    let mut state = GameState::from_move_list("4.4 4.0").unwrap();
+    assert_eq!(state.active_boards(), [[0]]);
+
+    // X wins board 0 and O is sent back there, every other open board is active
+    for index in [[0, 1], [8, 8], [0, 2], [8, 7], [0, 0]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let active = state.active_boards();
+    assert_eq!(active, (1..INNER_BOARDS).map(|board| [board]).collect::<Vec<_>>());
+
+    // A full board without a winner is left out as well
+    let drawn = BitBoard::from_bits(0b010_001_101, 0b101_110_010).unwrap();
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    meta_board.sub_boards[3] = Board::BitBoard(drawn);
+    assert!(!state.active_boards().contains(&[3]));
+    assert_eq!(state.active_boards().len(), INNER_BOARDS - 2);
+
+    let over = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8 8.7"
+    ).unwrap();
+    assert!(over.active_boards().is_empty());
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_canonical_moves() {
+    let mut moves = PossibleMoves::new();
+    GameState::new().canonical_moves(&mut moves);
+    // The symmetries act on the board and the cell at once. By Burnside's lemma
+    // (81 + 3 * 1 + 4 * 3²) / 8 = 15 of the 81 opening moves are different.
+    assert_eq!(moves.len(), 15);
+    // In the center board only a corner, an edge and the center are left
+    let center: Vec<_> = moves.into_iter().filter(|meta_move| meta_move.board() == [4]).map(|meta_move| meta_move.cell()).collect();
+    assert_eq!(center, [0, 1, 4]);
+    assert_eq!(MetaMove::new(&[4, 8]).canonical(&GameState::new()).absolute_index, [4, 0]);
+    assert_eq!(MetaMove::new(&[8, 8]).canonical(&GameState::new()).absolute_index, [0, 0]);
+
+    // X in the center sends O to the center board, which is still symmetric
+    let state = GameState::from_move_list("4.4").unwrap();
+    state.canonical_moves(&mut moves);
+    assert_eq!(moves.into_iter().map(|meta_move| meta_move.to_string()).collect::<Vec<_>>(), ["4.0", "4.1"]);
+
+    // The transpose keeps 4.4 4.0 in place and sends to board 0 again
+    let state = GameState::from_move_list("4.4 4.0").unwrap();
+    state.canonical_moves(&mut moves);
+    assert_eq!(moves.len(), 6);
+    assert_eq!(MetaMove::new(&[0, 3]).canonical(&state).absolute_index, [0, 1]);
+
+    // Nothing is symmetric anymore
+    let state = GameState::from_move_list("0.4 4.1").unwrap();
+    state.canonical_moves(&mut moves);
+    assert_eq!(moves.len(), state.legal_moves().len());
+    assert_eq!(MetaMove::new(&[1, 8]).canonical(&state).absolute_index, [1, 8]);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_perft() {
+    // Up to 4 plies no board can be won, the counts follow from the empty cells alone.
+    // At 5 plies X can win a sub board and the next player may choose the board.
+    let mut state = GameState::new();
+    for (depth, count) in [1, 81, 720, 6336, 55080, 473256].into_iter().enumerate() {
+        assert_eq!(state.perft(depth as u32), count, "depth {}", depth);
+    }
+    assert!(state == GameState::new());
+    assert_eq!(state.ply(), 0);
+
+    // No moves after the game is decided
+    let mut won = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8 8.7"
+    ).unwrap();
+    assert_eq!(won.perft(1), 0);
+    assert_eq!(won.perft(0), 1);
+}
+
+#[test]
+fn test_undo_to_ply() {
+    let mut state = GameState::new();
+    for index in [[4, 4], [4, 0], [0, 4], [4, 8], [8, 8]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let latest = state.clone();
+
+    state.undo_to_ply(2);
+    assert_eq!(state.ply(), 2);
+    assert_eq!(state.last_move, Some(MetaMove::new(&[4, 0])));
+
+    state.undo_to_ply(0);
+    assert!(state == GameState::new());
+    assert_eq!(state.undo(), None);
+
+    while state.redo().is_some() {}
+    assert!(state == latest);
+    assert_eq!(state.ply(), latest.ply());
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_undo_redo_round_trip() {
+    // Wins several sub boards and ends with the winning move
+    let moves = "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+                 5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8 8.7";
+    let mut state = GameState::new();
+    let mut snapshots = vec![state.clone()];
+    for meta_move in moves.split_whitespace() {
+        state.set(meta_move.parse().unwrap()).unwrap();
+        snapshots.push(state.clone());
+    }
+    assert!(state.outcome().is_some());
+
+    // Every undo restores the board, the side to move and the forced board
+    for snapshot in snapshots.iter().rev().skip(1) {
+        state.undo().unwrap();
+        assert!(state == *snapshot);
+        assert_eq!(state.current_player, snapshot.current_player);
+        assert_eq!(state.last_move.map(|m| m.absolute_index), snapshot.last_move.map(|m| m.absolute_index));
+        assert_eq!(state.outcome(), snapshot.outcome());
+    }
+    assert_eq!(state.undo(), None);
+
+    for snapshot in snapshots.iter().skip(1) {
+        state.redo().unwrap();
+        assert!(state == *snapshot);
+        assert_eq!(state.current_player, snapshot.current_player);
+    }
+    assert_eq!(state.redo(), None);
+}
+
+#[test]
+fn test_notation_round_trip() {
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    for seed in 0..20 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut state = GameState::new();
+        loop {
+            let notation = state.to_notation();
+            let parsed = GameState::from_notation(&notation).unwrap();
+            assert!(parsed == state, "{}", notation);
+            assert_eq!(parsed.to_notation(), notation);
+            assert_eq!(parsed.zobrist_hash(), state.zobrist_hash());
+            assert_eq!(parsed.outcome(), state.outcome());
+            assert_eq!(parsed.legal_moves().len(), state.legal_moves().len());
+
+            let moves = state.legal_moves();
+            let moves: Vec<MetaMove> = moves.into_iter().copied().collect();
+            match moves.choose(&mut rng) {
+                Some(&meta_move) => state.set(meta_move).unwrap(),
+                None => break,
+            };
+        }
+    }
+}
+
+#[test]
+fn test_notation_invalid() {
+    let notation = GameState::from_move_list("4.4").unwrap().to_notation();
+    assert!(notation.ends_with(" O 4.4"));
+    assert!(GameState::from_notation(&notation.replace(" O ", " X ")).is_err());
+    assert!(GameState::from_notation(&notation.replace(" 4.4", " 4.5")).is_err());
+    assert!(GameState::from_notation(&notation.replace(" 4.4", "")).is_err());
+    assert!(GameState::from_notation(&notation.replacen('_', "", 1)).is_err());
+    assert!(GameState::from_notation(&notation.replacen('_', "?", 1)).is_err());
+    assert!(GameState::from_notation("").is_err());
+}
+
+#[test]
+fn test_possible_moves_shuffle() {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    let mut possible_moves = PossibleMoves::new();
+    for move_ in GameState::new().legal_moves().into_iter().take(BOARD_SIZE_SQUARED) {
+        possible_moves.push(*move_);
+    }
+    let before: Vec<MetaMove> = possible_moves.into_iter().copied().collect();
+
+    possible_moves.shuffle(&mut StdRng::seed_from_u64(1));
+    let mut after: Vec<MetaMove> = possible_moves.into_iter().copied().collect();
+    assert_ne!(after, before);
+
+    after.sort_by_key(|move_| move_.absolute_index);
+    assert_eq!(after, before);
+    for i in possible_moves.len()..META_SIZE {
+        assert_eq!(possible_moves[i], MetaMove::default());
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_threat_map() {
+    let mut state = GameState::new();
+    // X can complete [0, 2]
+    for index in [[0, 0], [0, 3], [0, 1], [0, 4], [5, 5], [8, 0]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let map = state.threat_map();
+
+    let winning_cell = MetaMove::new(&[0, 2]).cell_index();
+    assert_eq!(map[winning_cell], 1.);
+    assert!(map.iter().all(|&score| score <= map[winning_cell]));
+    assert_eq!(map[MetaMove::new(&[0, 5]).cell_index()], 0.75);
+
+    for index in [[0, 0], [0, 3], [0, 1], [0, 4], [5, 5], [8, 0]] {
+        assert_eq!(map[MetaMove::new(&index).cell_index()], 0.);
+    }
+}
+
+#[test]
+fn test_board_depth() {
+    assert_eq!(Board::new().depth(), META_DEPTH);
+    assert_eq!(Board::BitBoard(BitBoard::new()).depth(), 1);
+
+    let board = Board::MetaBoard(MetaBoard {
+        board: BitBoard::new(),
+        sub_boards: Box::new([(); BOARD_SIZE_SQUARED].map(|_| Board::BitBoard(BitBoard::new()))),
+        drawn: 0,
+        drawn_board_rule: DrawnBoardRule::Neutral,
+        winner: PlayerMarker::Empty,
+    });
+    assert_eq!(board.depth(), 2);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_won_sub_boards() {
+    let mut state = GameState::new();
+    assert!(state.won_sub_boards().is_empty());
+
+    for index in [[0, 0], [4, 3], [0, 1], [4, 4], [0, 2], [4, 5]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(state.won_sub_boards(), vec![(0, PlayerMarker::X), (4, PlayerMarker::O)]);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_bitboard_from_bits() {
+    let bit_board = BitBoard::from_bits(0b000_000_111, 0b000_011_000).unwrap();
+    assert_eq!(bit_board.get(0), PlayerMarker::X);
+    assert_eq!(bit_board.get(3), PlayerMarker::O);
+    assert_eq!(bit_board.get(8), PlayerMarker::Empty);
+    assert_eq!(bit_board.get_winner(), PlayerMarker::X);
+
+    assert!(BitBoard::from_bits(0b000_000_011, 0b000_000_010).is_err());
+    assert!(BitBoard::from_bits(0b1_000_000_000, 0).is_err());
+    assert!(BitBoard::from_bits(0, 0b1_000_000_000_000).is_err());
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_meta_move_transform() {
+    for cell_index in 0..META_SIZE {
+        let meta_move = MetaMove::from_cell_index(cell_index);
+        assert_eq!(meta_move.cell_index(), cell_index);
+        for symmetry in 0..8 {
+            let transformed = meta_move.transform(symmetry);
+            assert!((0..8).any(|inverse| transformed.transform(inverse) == meta_move));
+        }
+    }
+    assert_eq!(MetaMove::new(&[0, 1]).transform(1).absolute_index, [2, 5]);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_key_for_book() {
+    let mut state = GameState::new();
+    state.set(MetaMove::new(&[4, 4])).unwrap();
+    state.set(MetaMove::new(&[4, 0])).unwrap();
+
+    let key = state.key_for_book();
+    assert_eq!(key, "01000024000000000000000000804000000000000000000000");
+    assert_eq!(key, state.book_key(BOOK_KEY_VERSION));
+    assert_ne!(key, state.book_key(BOOK_KEY_VERSION + 1));
+
+    // Symmetric positions share the key
+    let mut rotated = GameState::new();
+    rotated.set(MetaMove::new(&[4, 4])).unwrap();
+    rotated.set(MetaMove::new(&[4, 2])).unwrap();
+    assert_eq!(rotated.key_for_book(), key);
+
+    let mut other = GameState::new();
+    other.set(MetaMove::new(&[4, 4])).unwrap();
+    other.set(MetaMove::new(&[4, 1])).unwrap();
+    assert_ne!(other.key_for_book(), key);
+}
+
+#[test]
+fn test_render_matches_display() {
+    let mut state = GameState::new();
+    let mut rendered = vec![];
+    state.render(&mut rendered).unwrap();
+    assert_eq!(rendered, state.to_string().into_bytes());
+
+    // Won sub boards are drawn differently than open ones
+    for index in [[0, 0], [4, 3], [0, 1], [4, 4], [0, 2], [4, 5], [8, 8]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let mut rendered = vec![];
+    state.render(&mut rendered).unwrap();
+    assert_eq!(rendered, state.to_string().into_bytes());
+}
+
+#[test]
+fn test_render_unicode() {
+    let state = GameState::from_move_list("4.4 4.0").unwrap();
+    let mut rendered = vec![];
+    state.render_with(&mut rendered, DisplayOptions::default().with_style(DisplayStyle::Unicode)).unwrap();
+    let rendered = String::from_utf8(rendered).unwrap();
+    assert!(rendered.contains('┼') && rendered.contains('─') && rendered.contains('│'));
+    assert!(!rendered.contains(['|', '-', '+']));
+    // The frame keeps the grid square
+    let lines: Vec<&str> = rendered.lines().collect();
+    assert_eq!(lines.len(), DISPLAY_SIZE + 2);
+    assert!(lines.iter().all(|line| line.chars().count() == lines.len()));
+    assert_eq!(rendered.matches('.').count(), META_SIZE - 2);
+
+    let mut ascii = vec![];
+    state.render_with(&mut ascii, DisplayOptions::default()).unwrap();
+    assert_eq!(ascii, state.to_string().into_bytes());
+}
+
+#[test]
+fn test_render_coordinates() {
+    let digits: String = (0..BOARD_SIZE).map(|index| char::from_digit(index as u32, 10).unwrap()).collect();
+    for style in [DisplayStyle::Ascii, DisplayStyle::Unicode] {
+        let options = DisplayOptions::default().with_style(style).with_coordinates(true);
+        for depth in [1, 2] {
+            let mut rendered = vec![];
+            Board::create_board(depth).render_with(&mut rendered, options).unwrap();
+            let rendered = String::from_utf8(rendered).unwrap();
+            let lines: Vec<Vec<char>> = rendered.lines().map(|line| line.chars().collect()).collect();
+            assert!(lines.iter().all(|line| line.len() == lines[0].len()));
+
+            // The outer board in the first header row, every label above a column of cells
+            let header: String = lines[0].iter().filter(|&&label| label != ' ').collect();
+            assert_eq!(header, digits);
+            let first_row = &lines[depth + (style == DisplayStyle::Unicode) as usize];
+            for (col, &label) in lines[0].iter().enumerate() {
+                if label != ' ' {
+                    assert_eq!(first_row[col], '.', "{} is not above a cell", label);
+                }
+            }
+            // The rows of the outer board in the first column
+            let row_labels: String = lines.iter().map(|line| line[0]).filter(|&label| label != ' ').collect();
+            assert_eq!(row_labels, digits);
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_render_highlighted() {
+    let paint = |symbol: &str, highlight: Highlight| match highlight {
+        Highlight::LastMove => format!("<{}>", symbol),
+        Highlight::Decided(PlayerMarker::X) => format!("x{}", symbol),
+        Highlight::Decided(PlayerMarker::O) => format!("o{}", symbol),
+        Highlight::Decided(_) => format!("d{}", symbol),
+        Highlight::Active => format!("a{}", symbol),
+    };
+    let state = GameState::from_move_list("4.4 4.0").unwrap();
+    let rendered = state.render_painted(paint);
+    assert!(rendered.contains("<O>"));
+    assert!(!rendered.contains("<X>"));
+    // The empty board 0 is the only board of the next move
+    assert_eq!(rendered.matches("a.").count(), BOARD_SIZE_SQUARED);
+
+    // Without highlights it is the plain board
+    assert_eq!(state.render_painted(|symbol, _| symbol.to_string()), state.to_string());
+
+    // Decided boards in the colors of their winner
+    let mut state = state;
+    for index in [[0, 0], [8, 0], [0, 1], [8, 1], [0, 2], [8, 2], [1, 0]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let drawn = BitBoard::from_bits(0b010_001_101, 0b101_110_010).unwrap();
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    meta_board.sub_boards[2] = Board::BitBoard(drawn);
+    let rendered = state.render_painted(paint);
+    // The winner stamps 5 cells, the rest of the region is blank
+    assert_eq!(rendered.matches("xX").count(), 5);
+    assert_eq!(rendered.matches("x ").count(), BOARD_SIZE_SQUARED - 5);
+    assert_eq!(rendered.matches("oO").count(), 5);
+    assert_eq!(rendered.matches("dX").count(), 4);
+    assert_eq!(rendered.matches("dO").count(), 5);
+
+    assert_eq!(display_region(&[]), (0, 0, DISPLAY_SIZE));
+    assert_eq!(display_region(&[4]), (4, 4, 3));
+    assert_eq!(display_region(&[4, 5]), (5, 6, 1));
+}
+
+#[test]
+fn test_display_layout() {
+    // Every cell is drawn once, whatever the board size
+    let rendered = GameState::new().to_string();
+    assert_eq!(rendered.lines().count(), DISPLAY_SIZE);
+    assert!(rendered.lines().all(|line| line.chars().count() == DISPLAY_SIZE));
+    assert_eq!(rendered.matches('.').count(), META_SIZE);
+    assert_eq!(sub_board_layout(META_DEPTH, DISPLAY_SIZE).0 * BOARD_SIZE + (BOARD_SIZE - 1) * (META_DEPTH - 1), DISPLAY_SIZE);
+}
+
+#[test]
+fn test_display_separators() {
+    for depth in [2, 3] {
+        let size = Board::calculate_display_size(depth);
+        let mut array = vec![vec![' '; size]; size];
+        Board::create_board(depth).fill_board(&mut array, (0, 0), depth, size);
+        let rows: Vec<String> = array.iter().map(|row| row.iter().collect()).collect();
+        assert!(rows.iter().all(|row| row.chars().count() == size));
+        assert_eq!(rows.concat().matches('.').count(), BOARD_SIZE_SQUARED.pow(depth as u32));
+        assert!(!rows.concat().contains(' '));
+
+        if depth == 2 {
+            // One line between neighbouring sub boards, across the cell rows and columns
+            let cell_rows = BOARD_SIZE * BOARD_SIZE;
+            assert_eq!(rows.concat().matches('|').count(), (BOARD_SIZE - 1) * cell_rows);
+            assert_eq!(rows.concat().matches('-').count(), (BOARD_SIZE - 1) * cell_rows);
+            assert_eq!(rows.concat().matches('+').count(), (BOARD_SIZE - 1) * (BOARD_SIZE - 1));
+            assert_eq!(rows[BOARD_SIZE], vec!["-".repeat(BOARD_SIZE); BOARD_SIZE].join("+"));
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_is_forced_draw() {
+    // X O X / X O O / O X - has one empty cell but no open line
+    let dead = BitBoard::from_bits(0b010_001_101, 0b001_110_010).unwrap();
+    assert!(Board::BitBoard(dead).is_dead());
+
+    let mut state = GameState::new();
+    assert!(!state.is_forced_draw());
+
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    meta_board.sub_boards[0] = Board::BitBoard(dead);
+    meta_board.sub_boards[8] = Board::BitBoard(dead);
+    // The middle row and column are still open
+    assert!(!state.is_forced_draw());
+
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    meta_board.sub_boards[4] = Board::BitBoard(dead);
+    // Every meta line passes through board 0, 4 or 8
+    assert!(state.is_forced_draw());
+    // No sub board is won, so no line is taken by both players
+    assert!(!state.is_drawn_by_exhaustion());
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_open_lines() {
+    assert_eq!(BitBoard::new().open_lines(PlayerMarker::X), WINNING_LINES as u8);
+
+    // X in the center blocks the middle row and column and both diagonals for O
+    let center = BitBoard::from_bits(0b000_010_000, 0).unwrap();
+    assert_eq!(center.open_lines(PlayerMarker::X), 8);
+    assert_eq!(center.open_lines(PlayerMarker::O), 4);
+
+    // O in a corner takes another row, column and diagonal from X
+    let contested = BitBoard::from_bits(0b000_010_000, 0b000_000_001).unwrap();
+    assert_eq!(contested.open_lines(PlayerMarker::X), 5);
+    assert_eq!(contested.open_lines(PlayerMarker::O), 4);
+    assert_eq!(contested.open_lines(PlayerMarker::Empty), 0);
+
+    let mut state = GameState::new();
+    assert_eq!(state.line_pressure(), (8, 8));
+    for index in [[4, 0], [0, 4], [4, 1], [1, 4], [4, 2]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    // X won the center board
+    assert_eq!(state.line_pressure(), (8, 4));
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_is_drawn_by_exhaustion() {
+    // Sub boards won as X O X / X O O / O X -, every meta line has an X and an O
+    let (x, o) = (0b010_001_101, 0b001_110_010);
+    let mut state = GameState::new();
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    for i in 0..BOARD_SIZE_SQUARED {
+        if x & 1 << i != 0 {
+            meta_board.sub_boards[i] = Board::BitBoard(BitBoard::from_bits(0b111, 0).unwrap());
+        } else if o & 1 << i != 0 {
+            meta_board.sub_boards[i] = Board::BitBoard(BitBoard::from_bits(0, 0b111).unwrap());
+        }
+    }
+    meta_board.board = BitBoard::from_bits(x, o).unwrap();
+
+    assert!(state.is_drawn_by_exhaustion());
+    assert!(state.is_forced_draw());
+    assert_eq!(state.outcome(), Some(GameOutcome::Draw));
+    // Board 8 is still empty, but can't be played anymore
+    assert_eq!(state.cell(&[8, 4]), Ok(PlayerMarker::Empty));
+    assert!(!state.board.can_set());
+    assert!(state.legal_moves().is_empty());
+    assert!(!state.is_legal(MetaMove::new(&[8, 4])));
+
+    // O didn't win board 6, the anti-diagonal 2, 4, 6 is open for X again
+    let Board::MetaBoard(meta_board) = &mut state.board else { unreachable!() };
+    meta_board.sub_boards[6] = Board::BitBoard(BitBoard::new());
+    meta_board.board = BitBoard::from_bits(x, o & !(1 << 6)).unwrap();
+    assert!(!state.is_drawn_by_exhaustion());
+    assert_eq!(state.outcome(), None);
+    assert!(state.board.can_set());
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_meta_move_from_coords() {
+    let meta_move = MetaMove::from_coords("2.5").unwrap();
+    assert_eq!(meta_move.absolute_index, [2, 5]);
+    assert_eq!(meta_move.to_string(), "2.5");
+    assert_eq!(MetaMove::from_coords("8.0 ").unwrap_err().to_string(), "Invalid move '8.0 '");
+
+    // Out of range
+    assert!(MetaMove::from_coords("9.0").is_err());
+    assert!(MetaMove::from_coords("0.10").is_err());
+    assert!(MetaMove::from_coords("-1.0").is_err());
+    // Malformed
+    for input in ["", ".", "4.", ".4", "4..4", "4,4", "a.b", "4.4.", "4.4.4"] {
+        assert!(MetaMove::from_coords(input).is_err(), "{}", input);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_meta_move_try_new() {
+    assert_eq!(MetaMove::try_new(&[2, 5]), Ok(MetaMove::new(&[2, 5])));
+    assert_eq!(MetaMove::try_new(&[2]), Err(InvalidMove::IndexTooShort));
+    assert_eq!(MetaMove::try_new(&[]), Err(InvalidMove::IndexTooShort));
+    assert_eq!(MetaMove::try_new(&[2, 5, 1]), Err(InvalidMove::OutOfBounds));
+    assert_eq!(MetaMove::try_new(&[2, 9]), Err(InvalidMove::OutOfBounds));
+    assert_eq!(MetaMove::try_new(&[usize::MAX, 0]), Err(InvalidMove::OutOfBounds));
+
+    let mut meta_move = MetaMove::new_empty();
+    assert!(meta_move.try_push(9).is_err());
+    meta_move.try_push(3).unwrap();
+    meta_move.try_push(8).unwrap();
+    assert_eq!(meta_move.absolute_index, [3, 8]);
+    assert_eq!(meta_move.try_push(0), Err(InvalidMove::OutOfBounds));
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_from_move_list() {
+    assert_eq!("7.4".parse::<MetaMove>(), Ok(MetaMove::new(&[7, 4])));
+    assert!("7".parse::<MetaMove>().is_err());
+    assert!("7.4.1".parse::<MetaMove>().is_err());
+    assert!("7.9".parse::<MetaMove>().is_err());
+
+    let state = GameState::from_move_list("4.4\n4.0  0.8").unwrap();
+    assert_eq!(state.ply(), 3);
+    assert_eq!(state.last_move, Some(MetaMove::new(&[0, 8])));
+    // 4.0 sends O to board 0, so 5.0 is not allowed
+    assert!(GameState::from_move_list("4.4 4.0 5.0").is_err());
+}
+
+#[test]
+fn test_flip_and_augment() {
+    let mut state = GameState::from_move_list("4.4 4.0 0.8").unwrap();
+    // O is sent to board 8, make 8.2 the best move
+    let best_move = MetaMove::new(&[8, 2]);
+    let mut policy = [0.; META_SIZE];
+    policy[best_move.cell_index()] = 0.75;
+    policy[MetaMove::new(&[8, 0]).cell_index()] = 0.25;
+
+    let samples = state.flip_and_augment(&policy, 0.5);
+    assert_eq!(samples.len(), 8);
+    assert!(samples[0].0 == state);
+    assert_eq!(samples[0].1, policy);
+
+    state.set(best_move).unwrap();
+    for (symmetry, (mut sample, sample_policy, value)) in samples.into_iter().enumerate() {
+        assert_eq!(value, 0.5);
+        assert_eq!(sample_policy.iter().sum::<f32>(), 1.);
+        let best_index = (0..META_SIZE).find(|&i| sample_policy[i] == 0.75).unwrap();
+        let sample_move = MetaMove::from_cell_index(best_index);
+        assert!(sample.legal_moves().into_iter().any(|m| m.absolute_index == sample_move.absolute_index));
+
+        // Playing the remapped best move gives the transformed position after the best move
+        sample.set(sample_move).unwrap();
+        assert!(sample == state.transform(symmetry));
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_live_meta_lines() {
+    let mut state = GameState::new();
+    assert_eq!(state.live_meta_lines(PlayerMarker::X), 0b1111_1111);
+
+    // X wins board 0, O wins board 4
+    for index in [[0, 0], [4, 3], [0, 1], [4, 4], [0, 2], [4, 5]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    // Only the outer rows and columns avoid the center
+    assert_eq!(state.live_meta_lines(PlayerMarker::X), 0b0010_1101);
+    // Everything except the lines through the top left corner
+    assert_eq!(state.live_meta_lines(PlayerMarker::O), 0b1001_1011);
+    assert_eq!(state.live_meta_lines(PlayerMarker::Empty), 0);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_winner_or_draw_text() {
+    let mut state = GameState::new();
+    assert_eq!(state.winner_or_draw_text(), "In progress");
+
+    // The first player wins the boards 0, 1 and 2 of the top row
+    let top_row = [[0, 0], [5, 0], [0, 1], [5, 1], [0, 2], [6, 0], [1, 0], [6, 1], [1, 1], [6, 3], [1, 2], [7, 0], [2, 0], [7, 1], [2, 1], [7, 3], [2, 2]];
+    let mut x_wins = state.clone();
+    for index in top_row {
+        x_wins.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(x_wins.winner_or_draw_text(), "X wins");
+
+    // The same with the roles swapped after an opening move of X in board 8
+    let mut o_wins = state.clone();
+    o_wins.set(MetaMove::new(&[8, 4])).unwrap();
+    for index in top_row {
+        o_wins.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(o_wins.winner_or_draw_text(), "O wins");
+
+    // Every sub board drawn, no move is left but nobody won
+    for board in 0..9 {
+        for cell in [0, 1, 2, 4, 3, 5, 7, 6, 8] {
+            state.set(MetaMove::new(&[board, cell])).unwrap();
+        }
+    }
+    assert_eq!(state.winner_or_draw_text(), "Draw");
+}
+
+#[test]
+fn test_winning_positions() {
+    assert_eq!(winning_positions::<8>(3, 3), [
+        0b111_000_000, 0b000_111_000, 0b000_000_111,
+        0b100_100_100, 0b010_010_010, 0b001_001_001,
+        0b100_010_001, 0b001_010_100,
+    ]);
+
+    // 4x4 with three in a row: 8 rows, 8 columns, 4 diagonals and 4 anti-diagonals
+    const LINES: usize = winning_lines(4, 3);
+    const POSITIONS: [Bits; LINES] = winning_positions(4, 3);
+    assert_eq!(LINES, 24);
+    assert!(POSITIONS.iter().all(|pos| pos.count_ones() == 3 && pos >> 16 == 0));
+    for (i, pos) in POSITIONS.iter().enumerate() {
+        assert!(!POSITIONS[..i].contains(pos));
+    }
+}
+
+#[test]
+fn test_4x4_win_detection() {
+    const POSITIONS: [Bits; winning_lines(4, 3)] = winning_positions(4, 3);
+    let full_board = 0xffff;
+    let winner = |x, o| find_winner(x, o, full_board, &POSITIONS);
+
+    // Fields are numbered row by row, 4 per row
+    assert_eq!(winner(0b0000_0000_0000_0000, 0), PlayerMarker::Empty);
+    assert_eq!(winner(0b0000_0000_0111_0000, 0), PlayerMarker::X);
+    assert_eq!(winner(0b0000_0000_1110_0000, 0), PlayerMarker::X);
+    // Three in a row, but wrapped over two rows
+    assert_eq!(winner(0b0000_0001_1000_0000, 0b1100), PlayerMarker::Empty);
+    assert_eq!(winner(0, 0b0100_0100_0100_0000), PlayerMarker::O);
+    // Diagonal 1, 6, 11 and anti-diagonal 7, 10, 13
+    assert_eq!(winner(0b0000_1000_0100_0010, 0), PlayerMarker::X);
+    assert_eq!(winner(0, 0b0010_0100_1000_0000), PlayerMarker::O);
+    // Only two of three
+    assert_eq!(winner(0b0000_0000_0100_0010, 0b1000_0000_0000_0000), PlayerMarker::Empty);
+    // X X O O / O O X X / X X O O / O O X X
+    assert_eq!(winner(0b1100_0011_1100_0011, 0b0011_1100_0011_1100), PlayerMarker::Draw);
+}
+
+/// Win detection of the real bit board at 4x4, run with `--features board-4x4`
+#[cfg(feature = "board-4x4")]
+#[test]
+fn test_4x4_bit_board() {
+    let board = |x: &[usize], o: &[usize]| {
+        let mut board = BitBoard::new();
+        for &cell in x {
+            board.set(cell, PlayerMarker::X).unwrap();
+        }
+        for &cell in o {
+            board.set(cell, PlayerMarker::O).unwrap();
+        }
+        board
+    };
+
+    assert_eq!(WINNING_POSITIONS, winning_positions::<24>(4, 3));
+    assert_eq!(board(&[4, 5], &[]).get_winner(), PlayerMarker::Empty);
+    assert_eq!(board(&[5, 6, 7], &[]).get_winner(), PlayerMarker::X);
+    // Three in a row, but wrapped over two rows
+    assert_eq!(board(&[2, 3, 4], &[]).get_winner(), PlayerMarker::Empty);
+    assert_eq!(board(&[], &[3, 7, 11]).get_winner(), PlayerMarker::O);
+    assert_eq!(board(&[1, 6, 11], &[]).get_winner(), PlayerMarker::X);
+    assert_eq!(board(&[], &[7, 10, 13]).get_winner(), PlayerMarker::O);
+    assert_eq!(board(&[15], &[]).get_winner(), PlayerMarker::Empty);
+    assert_eq!(BitBoard::new().set(16, PlayerMarker::X), Err(InvalidMove::OutOfBounds));
+
+    // A full board is a draw, as on 3x3
+    let x = [0, 1, 6, 7, 8, 9, 14, 15];
+    let o = [2, 3, 4, 5, 10, 11, 12, 13];
+    let full = board(&x, &o);
+    assert_eq!(full.get_winner(), PlayerMarker::Draw);
+    assert_eq!(full, BitBoard::from_bits(0b1100_0011_1100_0011, 0b0011_1100_0011_1100).unwrap());
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_last_cell_completes_line() {
+    // X O X
+    // O X O
+    // O X _, X takes the last cell and completes the diagonal
+    let mut board = BitBoard::new();
+    for (cell, marker) in [(0, PlayerMarker::X), (1, PlayerMarker::O), (2, PlayerMarker::X), (3, PlayerMarker::O), (4, PlayerMarker::X), (5, PlayerMarker::O), (7, PlayerMarker::X), (6, PlayerMarker::O)] {
+        board.set(cell, marker).unwrap();
+    }
+    assert_eq!(board.get_winner(), PlayerMarker::Empty);
+    assert_eq!(board.winner_after(8, PlayerMarker::X), PlayerMarker::X);
+    board.set(8, PlayerMarker::X).unwrap();
+    assert_eq!(board.get_winner(), PlayerMarker::X);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_possible_moves_capacity() {
+    // Fill board 8 without a winner, the last move sends to the full board 8
+    let mut state = GameState::new();
+    for cell in [0, 1, 2, 4, 3, 5, 7, 6, 8] {
+        state.set(MetaMove::new(&[8, cell])).unwrap();
+    }
+    let legal_moves = state.legal_moves();
+    assert_eq!(legal_moves.len(), META_SIZE - BOARD_SIZE_SQUARED);
+    assert!(legal_moves.into_iter().all(|meta_move| meta_move.absolute_index[0] != 8));
+
+    // Every cell open at once still fits
+    let mut possible_moves = PossibleMoves::new();
+    for cell_index in 0..META_SIZE {
+        possible_moves.push(MetaMove::from_cell_index(cell_index));
+    }
+    assert_eq!(possible_moves.len(), META_SIZE);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_outcome() {
+    let mut state = GameState::new();
+    assert_eq!(state.outcome(), None);
+
+    let top_row = [[0, 0], [5, 0], [0, 1], [5, 1], [0, 2], [6, 0], [1, 0], [6, 1], [1, 1], [6, 3], [1, 2], [7, 0], [2, 0], [7, 1], [2, 1], [7, 3], [2, 2]];
+    let mut x_wins = state.clone();
+    for (i, index) in top_row.into_iter().enumerate() {
+        assert_eq!(x_wins.outcome(), None, "decided before move {}", i);
+        x_wins.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(x_wins.outcome(), Some(GameOutcome::Win(PlayerMarker::X)));
+
+    // No cell left and no winner
+    for board in 0..9 {
+        for cell in [0, 1, 2, 4, 3, 5, 7, 6, 8] {
+            state.set(MetaMove::new(&[board, cell])).unwrap();
+        }
+    }
+    assert!(!state.board.can_set());
+    assert_eq!(state.outcome(), Some(GameOutcome::Draw));
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_is_legal() {
+    let mut state = GameState::new();
+    assert!(state.is_legal(MetaMove::new(&[4, 4])));
+
+    // Sent to board 4
+    state.set(MetaMove::new(&[0, 4])).unwrap();
+    assert!(state.is_legal(MetaMove::new(&[4, 0])));
+    assert!(!state.is_legal(MetaMove::new(&[5, 0])));
+
+    // Board 8 is full, the last move sends to it, so every open board is legal
+    let mut state = GameState::new();
+    for cell in [0, 1, 2, 4, 3, 5, 7, 6, 8] {
+        state.set(MetaMove::new(&[8, cell])).unwrap();
+    }
+    assert!(state.is_legal(MetaMove::new(&[0, 0])));
+    assert!(state.is_legal(MetaMove::new(&[7, 8])));
+    assert!(!state.is_legal(MetaMove::new(&[8, 0])));
+
+    // Compare with the generated moves over random games
+    let rng = &mut StdRng::seed_from_u64(7);
+    for _ in 0..20 {
+        let mut state = GameState::new();
+        loop {
+            let legal_moves = state.legal_moves();
+            for cell_index in 0..META_SIZE {
+                let meta_move = MetaMove::from_cell_index(cell_index);
+                let generated = legal_moves.into_iter().any(|legal| legal.absolute_index == meta_move.absolute_index);
+                assert_eq!(state.is_legal(meta_move), generated, "{} after {:?}", meta_move, state.last_move);
+            }
+            if legal_moves.is_empty() {
+                break;
+            }
+            state.set(legal_moves[rng.gen_range(0..legal_moves.len())]).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_wins_game() {
+    // X wins boards 0 and 1 and has two cells of board 2, O has two cells of board 5
+    let mut state = GameState::new();
+    for index in [
+        [0, 0], [5, 0], [0, 1], [5, 1], [0, 2], [5, 3], [1, 0], [5, 4],
+        [1, 1], [6, 0], [1, 2], [6, 1], [2, 0], [7, 0], [2, 1], [7, 1],
+    ] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    assert!(state.wins_game(MetaMove::new(&[2, 2]), PlayerMarker::X));
+    assert!(!state.wins_game(MetaMove::new(&[2, 2]), PlayerMarker::O));
+    assert!(!state.wins_game(MetaMove::new(&[2, 3]), PlayerMarker::X));
+    // Winning a sub board alone is not enough
+    assert!(state.wins_sub_board(MetaMove::new(&[5, 2]), PlayerMarker::O));
+    assert!(!state.wins_game(MetaMove::new(&[5, 2]), PlayerMarker::O));
+
+    state.set(MetaMove::new(&[2, 2])).unwrap();
+    assert_eq!(state.get_winner(), PlayerMarker::X);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_wins_game_drawn_board_rule() {
+    // X won board 0 and board 1 is drawn, winning board 2 wins the game if the draw counts for X
+    let cells = ["XXX______", "XOXXOOOXO", "XX_______", "_________", "_________", "_________", "_________", "_________", "_________"];
+    for (rule, wins) in [
+        (DrawnBoardRule::Neutral, false),
+        (DrawnBoardRule::CountsForNeither, false),
+        (DrawnBoardRule::CountsForBoth, true),
+    ] {
+        let state = GameState::from_notation(&format!("{} X -", cells.join("/"))).unwrap().with_drawn_board_rule(rule);
+        assert_eq!(state.wins_game(MetaMove::new(&[2, 2]), PlayerMarker::X), wins, "{:?}", rule);
+        let mut played = state.clone();
+        played.set(MetaMove::new(&[2, 2])).unwrap();
+        assert_eq!(played.get_winner() == PlayerMarker::X, wins, "{:?}", rule);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_invalid_move_variants() {
+    // X wins board 4 and sends O to board 6
+    let mut state = GameState::from_move_list("4.4 4.0 0.0 0.4 4.8 8.4 4.2 2.4 4.6").unwrap();
+    assert_eq!(state.sub_board_winner(&[4]), PlayerMarker::X);
+    assert_eq!(state.check_move(MetaMove::new(&[6, 0])), Ok(()));
+    assert_eq!(state.check_move(MetaMove::new(&[3, 1])), Err(InvalidMove::WrongForcedBoard));
+
+    state.set(MetaMove::new(&[6, 4])).unwrap();
+    // Sent to board 4, which is won, so any open board may be chosen
+    assert_eq!(state.check_move(MetaMove::new(&[4, 1])), Err(InvalidMove::BoardDecided));
+    assert_eq!(state.check_move(MetaMove::new(&[6, 4])), Err(InvalidMove::CellOccupied));
+    assert_eq!(state.check_move(MetaMove::new(&[6, 9])), Err(InvalidMove::OutOfBounds));
+    assert_eq!(state.check_move(MetaMove::new(&[6, 1])), Ok(()));
+
+    // The same reasons from set and the index paths
+    assert_eq!(state.clone().set(MetaMove::new(&[6, 4])).err(), Some(InvalidMove::CellOccupied));
+    assert_eq!(state.clone().set(MetaMove::new(&[4, 1])).err(), Some(InvalidMove::BoardDecided));
+    assert_eq!(state.clone().set(MetaMove::new(&[6, 9])).err(), Some(InvalidMove::OutOfBounds));
+    assert_eq!(state.board.check_move(&[2], &[]), Err(InvalidMove::IndexTooShort));
+    assert_eq!(state.board.check_move(&[2, 1, 0], &[]), Err(InvalidMove::OutOfBounds));
+    assert_eq!(state.cell(&[2]), Err(InvalidMove::IndexTooShort));
+    assert_eq!(state.cell(&[2, 1, 0]), Err(InvalidMove::OutOfBounds));
+
+    assert_eq!(GameState::from_move_list("4.4 4.4").err(), Some(InvalidMove::CellOccupied));
+    assert_eq!(GameState::from_move_list("4.4 0.0").err(), Some(InvalidMove::WrongForcedBoard));
+    assert!(matches!(GameState::from_move_list("4.4 x"), Err(InvalidMove::Malformed(_))));
+}
+
+#[test]
+fn test_make_unmake_move() {
+    let mut state = GameState::new();
+    // X wins board 0, the last move also takes the meta cell
+    for index in [[0, 0], [4, 3], [0, 1], [4, 4], [0, 2]] {
+        state.make_move(MetaMove::new(&index)).unwrap();
+    }
+    let before = state.clone();
+    assert_eq!(state.won_sub_boards(), vec![(0, PlayerMarker::X)]);
+
+    assert_eq!(state.unmake_move(), Some(MetaMove::new(&[0, 2])));
+    assert!(state.won_sub_boards().is_empty());
+    assert_eq!(state.current_player, PlayerMarker::X);
+    assert_eq!(state.last_move, Some(MetaMove::new(&[4, 4])));
+    assert!(state.is_legal(MetaMove::new(&[4, 2])));
+
+    state.make_move_unchecked(MetaMove::new(&[0, 2]));
+    assert!(state == before);
+
+    while state.unmake_move().is_some() {}
+    assert!(state == GameState::new());
+    assert!(state.make_move(MetaMove::new(&[0, 0])).is_ok());
+    assert!(state.make_move(MetaMove::new(&[0, 0])).is_err());
+}
+
+/// Winner of the board from its cells alone, ignoring every cached winner
+#[cfg(test)]
+fn recomputed_winner(board: &Board) -> PlayerMarker {
+    match board {
+        Board::BitBoard(bit_board) => find_winner(bit_board.x, bit_board.o, FULL_BOARD, &WINNING_POSITIONS),
+        Board::MetaBoard(meta_board) => {
+            let mut fresh = meta_board.clone();
+            fresh.board = BitBoard::new();
+            fresh.drawn = 0;
+            for (i, sub_board) in meta_board.sub_boards.iter().enumerate() {
+                match recomputed_winner(sub_board) {
+                    marker @ (PlayerMarker::X | PlayerMarker::O) => {
+                        fresh.board.set_unchecked(i, marker);
+                    }
+                    PlayerMarker::Draw => fresh.drawn |= 1 << i,
+                    PlayerMarker::Empty => {}
+                }
+            }
+            fresh.compute_winner()
+        }
+    }
+}
+
+#[cfg(test)]
+fn assert_cached_winners(board: &Board) {
+    assert_eq!(board.get_winner(), recomputed_winner(board));
+    board.sub_boards().iter().for_each(assert_cached_winners);
+}
+
+#[test]
+fn test_cached_winners() {
+    use rand::seq::SliceRandom;
+
+    for (seed, rule) in [DrawnBoardRule::Neutral, DrawnBoardRule::CountsForBoth, DrawnBoardRule::CountsForNeither].into_iter().enumerate() {
+        let rng = &mut StdRng::seed_from_u64(seed as u64);
+        for _ in 0..10 {
+            let mut state = GameState::new().with_drawn_board_rule(rule);
+            while state.outcome().is_none() {
+                let moves: Vec<MetaMove> = state.moves().collect();
+                state.make_move_unchecked(*moves.choose(rng).unwrap());
+                assert_cached_winners(&state.board);
+            }
+            // Take back a few moves and play others, then take back everything
+            for _ in 0..rng.gen_range(1..10) {
+                state.unmake_move();
+                assert_cached_winners(&state.board);
+            }
+            while let Some(&meta_move) = state.moves().collect::<Vec<_>>().choose(rng) {
+                state.make_move_unchecked(meta_move);
+                assert_cached_winners(&state.board);
+                if state.outcome().is_some() {
+                    break;
+                }
+            }
+            while state.unmake_move().is_some() {
+                assert_cached_winners(&state.board);
+            }
+            assert!(state == GameState::new().with_drawn_board_rule(rule));
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_evaluate() {
+    let opening = GameState::new();
+    assert_eq!(opening.evaluate(), 0);
+
+    // X took the center of the center board, good for X and bad for O to move
+    let center = GameState::from_move_list("4.4").unwrap();
+    assert!(center.evaluate() < 0);
+    assert_eq!(HeuristicEvaluator.evaluate(&center), center.evaluate());
+
+    // The side to move wins the meta board with 8.7
+    let mut almost_won = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    assert!(almost_won.evaluate() > opening.evaluate());
+    assert!(almost_won.evaluate() > center.evaluate());
+
+    almost_won.set("8.7".parse().unwrap()).unwrap();
+    assert_eq!(almost_won.evaluate(), -WIN_EVALUATION);
+}
+
+#[test]
+fn test_zobrist_hash() {
+    // The same cells in a different order, both times 0.5 is the last move
+    let first = GameState::from_move_list("0.4 4.0 0.3 3.0 0.5").unwrap();
+    let second = GameState::from_move_list("0.3 3.0 0.4 4.0 0.5").unwrap();
+    assert!(first == second);
+    assert_eq!(first.zobrist_hash(), second.zobrist_hash());
+    assert_eq!(first.zobrist_hash(), first.compute_zobrist_hash());
+    assert_ne!(first.zobrist_hash(), GameState::from_move_list("0.4 4.0 0.3 3.0").unwrap().zobrist_hash());
+    assert_ne!(first.zobrist_hash(), GameState::new().zobrist_hash());
+
+    // Taking moves back restores the hash, also in random games with won sub boards
+    let mut rng = StdRng::seed_from_u64(3);
+    let mut state = GameState::new();
+    let mut hashes = vec![state.zobrist_hash()];
+    while state.outcome().is_none() {
+        let legal_moves = state.legal_moves();
+        state.make_move_unchecked(legal_moves[rng.gen_range(0..legal_moves.len())]);
+        assert_eq!(state.zobrist_hash(), state.compute_zobrist_hash());
+        hashes.push(state.zobrist_hash());
+    }
+    assert_eq!(state.transform(5).zobrist_hash(), state.transform(5).compute_zobrist_hash());
+    while state.unmake_move().is_some() {
+        hashes.pop();
+        assert_eq!(state.zobrist_hash(), *hashes.last().unwrap());
+    }
+    assert_eq!(state.zobrist_hash(), 0);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_drawn_board_rule() {
+    // X won boards 0 and 1, board 2 is drawn, O won boards 3 and 4
+    let drawn = "XOXXOOOXO";
+    let cells = ["XXX______", "XXX______", drawn, "OOO______", "OOO______", "_________", "_________", "_________", "_________"];
+    let state = |rule| GameState::from_notation(&format!("{} X -", cells.join("/"))).unwrap().with_drawn_board_rule(rule);
+    assert_eq!(GameState::new().drawn_board_rule(), DrawnBoardRule::Neutral);
+    assert_eq!(state(DrawnBoardRule::CountsForBoth).drawn_board_rule(), DrawnBoardRule::CountsForBoth);
+
+    // The top row only wins if the drawn board counts for X
+    for (rule, winner) in [
+        (DrawnBoardRule::Neutral, PlayerMarker::Empty),
+        (DrawnBoardRule::CountsForNeither, PlayerMarker::Empty),
+        (DrawnBoardRule::CountsForBoth, PlayerMarker::X),
+    ] {
+        let state = state(rule);
+        assert_eq!(state.get_winner(), winner, "{:?}", rule);
+        assert_eq!(state.board.can_set(), winner == PlayerMarker::Empty, "{:?}", rule);
+    }
+
+    // Every sub board is drawn, a line of drawn boards only belongs to nobody
+    let cells = [drawn; 9].join("/");
+    for (rule, winner) in [
+        (DrawnBoardRule::Neutral, PlayerMarker::Empty),
+        (DrawnBoardRule::CountsForNeither, PlayerMarker::Draw),
+        (DrawnBoardRule::CountsForBoth, PlayerMarker::Draw),
+    ] {
+        let state = GameState::from_notation(&format!("{} X -", cells)).unwrap().with_drawn_board_rule(rule);
+        assert_eq!(state.get_winner(), winner, "{:?}", rule);
+        assert!(!state.board.can_set());
+        assert_eq!(state.outcome(), Some(GameOutcome::Draw));
+    }
+
+    // Drawing board 2 by playing into it is noted like reading it from the cells
+    let mut played = GameState::from_notation(&format!("{} O 0.2", ["XXX______", "XXX______", "XOXXOOOX_", "OOO______", "OOO______", "_________", "_________", "_________", "_________"].join("/")))
+        .unwrap()
+        .with_drawn_board_rule(DrawnBoardRule::CountsForBoth);
+    // Whoever draws it, X wins
+    assert!(played.wins_game(MetaMove::new(&[2, 8]), PlayerMarker::X));
+    assert!(!played.wins_game(MetaMove::new(&[2, 8]), PlayerMarker::O));
+    played.set(MetaMove::new(&[2, 8])).unwrap();
+    assert_eq!(played.get_winner(), PlayerMarker::X);
+    played.undo();
+    assert_eq!(played.get_winner(), PlayerMarker::Empty);
+}
+
+#[test]
+fn test_moves() {
+    let mut state = GameState::new();
+    for meta_move in ["4.4", "4.0", "0.0", "0.4", "4.8", "8.4", "4.2", "2.4", "4.6"] {
+        let mut possible_moves = PossibleMoves::new();
+        state.get_possible_moves(&mut possible_moves, &mut MetaMove::new_empty());
+        assert_eq!(state.moves().count(), possible_moves.len());
+        assert!(state.moves().zip(&possible_moves).all(|(a, b)| a.absolute_index == b.absolute_index));
+        state.set(meta_move.parse().unwrap()).unwrap();
+    }
+    // X won board 4 and sends O to board 6
+    assert_eq!(state.get_winner(), PlayerMarker::Empty);
+    assert_eq!(state.moves().count(), state.legal_moves().len());
+    assert!(state.moves().all(|meta_move| meta_move.board() == [6]));
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_ruleset() {
+    assert_eq!(GameState::new().ruleset(), Ruleset::Standard);
+    let mut standard = GameState::new();
+    let mut free = GameState::new().with_ruleset(Ruleset::Free);
+    standard.set(MetaMove::new(&[4, 0])).unwrap();
+    free.set(MetaMove::new(&[4, 0])).unwrap();
+
+    assert_eq!(standard.legal_moves().len(), 9);
+    assert_eq!(free.legal_moves().len(), 80);
+    assert!(!standard.is_legal(MetaMove::new(&[8, 8])));
+    assert!(free.is_legal(MetaMove::new(&[8, 8])));
+    assert!(!free.is_legal(MetaMove::new(&[4, 0])));
+    assert!(standard != free);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_send_to_claimed() {
+    // X takes cells 0 and 1 of board 0, O is sent away from it in between
+    let play = |ruleset| GameState::from_move_list("0.0 0.4 4.4 4.0 0.1 1.0").unwrap().with_ruleset(ruleset);
+
+    // Without a won board both rulesets send X to the board of the cell
+    let (mut standard, mut claimed) = (play(Ruleset::Standard), play(Ruleset::SendToClaimed));
+    assert_eq!(standard.legal_moves().len(), 6);
+    assert_eq!(claimed.legal_moves().len(), 6);
+
+    // 0.2 wins board 0, Standard sends O to board 2, SendToClaimed back to the won board 0
+    standard.set(MetaMove::new(&[0, 2])).unwrap();
+    claimed.set(MetaMove::new(&[0, 2])).unwrap();
+    assert_eq!(standard.sub_board_winner(&[0]), PlayerMarker::X);
+    assert_eq!(standard.legal_moves().len(), 9);
+    assert!(standard.moves().all(|meta_move| meta_move.board() == [2]));
+    // Board 0 is decided, so every open cell of the other boards can be played
+    assert_eq!(claimed.legal_moves().len(), 69);
+    assert!(claimed.is_legal(MetaMove::new(&[8, 8])));
+    assert!(!claimed.is_legal(MetaMove::new(&[0, 5])));
+    assert!(!standard.is_legal(MetaMove::new(&[8, 8])));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_serde_round_trip() {
+    let state = GameState::from_move_list("4.4 4.0 0.0 0.4 4.8 8.4 4.2 2.4 4.6")
+        .unwrap()
+        .with_drawn_board_rule(DrawnBoardRule::CountsForNeither);
+    let json = serde_json::to_string(&state).unwrap();
+    assert!(json.starts_with("{\"board\":[[\"X\",\"Empty\","));
+    assert!(json.ends_with(",\"current_player\":\"O\",\"last_move\":[4,6],\"ruleset\":\"Standard\",\"drawn_board_rule\":\"CountsForNeither\"}"));
+
+    let parsed: GameState = serde_json::from_str(&json).unwrap();
+    assert!(parsed == state);
+    assert_eq!(parsed.zobrist_hash(), state.zobrist_hash());
+    assert_eq!(parsed.sub_board_winner(&[4]), PlayerMarker::X);
+    assert_eq!(parsed.drawn_board_rule(), DrawnBoardRule::CountsForNeither);
+    assert_eq!(parsed.legal_moves().len(), state.legal_moves().len());
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["board"][4][4], "X");
+    assert_eq!(value["board"].as_array().unwrap().len(), BOARD_SIZE_SQUARED);
+
+    // A cell of X can't be the last move of X with X to move
+    let invalid = json.replace("\"current_player\":\"O\"", "\"current_player\":\"X\"");
+    assert!(serde_json::from_str::<GameState>(&invalid).is_err());
+    assert!(serde_json::from_str::<MetaMove>("[4, 9]").is_err());
+    assert!(serde_json::from_str::<Board>("[[\"X\"]]").is_err());
 }
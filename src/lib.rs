@@ -0,0 +1,13 @@
+//! Meta tic-tac-toe, the nested game with players, engines and match statistics
+//! 
+//! Implement [`Player`] to let a bot play in a [`Game`]
+
+pub mod game;
+pub mod mcts;
+pub mod player;
+pub mod players;
+pub mod tournament;
+pub mod web;
+
+pub use game::{GameState, MetaMove};
+pub use player::{Game, GameBuilder, GameObserver, Player, PlayerAction};
@@ -0,0 +1,2750 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+// No threads in the browser, see `MonteCarloAsync` and `MonteCarloSync::with_threads`
+#[cfg(not(target_arch = "wasm32"))]
+use std::{sync::{atomic::{AtomicU64, Ordering}, mpsc::{channel, Receiver, Sender, TryRecvError}, Mutex, MutexGuard}, thread::{self, JoinHandle}, time::Instant};
+// `std::time::Instant` panics in the browser
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+#[cfg(test)]
+use std::fs;
+
+use rand::{distributions::{Distribution, WeightedIndex}, rngs::StdRng, Rng, RngCore, SeedableRng};
+
+use crate::{game::{GameState, MetaMove, PlayerMarker, PossibleMoves}, player::{Player, SearchInfo}};
+#[cfg(test)]
+use crate::{game::{GameOutcome, META_SIZE}, player::{GameRecord, MoveRecord}, players::{AlphaBetaPlayer, RandomPlayer}, tournament::run_tournament};
+
+// ##############################
+// # Engine
+// ##############################
+
+/// Budget of a single search, a limit of `None` is not checked
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    iterations: Option<u32>,
+    time: Option<Duration>,
+    depth: Option<u32>,
+}
+
+impl SearchLimits {
+    pub fn iterations(iterations: u32) -> Self {
+        SearchLimits { iterations: Some(iterations), ..Default::default() }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        SearchLimits { time: Some(time), ..Default::default() }
+    }
+
+    /// Plies of a fixed depth search like `AlphaBetaPlayer`, MCTS has no depth and ignores it
+    pub fn depth(depth: u32) -> Self {
+        SearchLimits { depth: Some(depth), ..Default::default() }
+    }
+
+    /// The depth limit, `None` if the search isn't limited by depth
+    pub fn max_depth(&self) -> Option<u32> {
+        self.depth
+    }
+
+    /// Share of the budget of one of `threads` searches running side by side, see `TimeControl::per_thread`
+    fn per_thread(self, threads: usize) -> SearchLimits {
+        let threads = threads.max(1) as u32;
+        SearchLimits { iterations: self.iterations.map(|limit| limit.div_ceil(threads)), ..self }
+    }
+
+    /// Check if the iteration or time budget is used up
+    fn is_reached(&self, iterations: u64, start: Instant) -> bool {
+        self.iterations.is_some_and(|limit| iterations >= limit as u64)
+            || self.time.is_some_and(|limit| start.elapsed() >= limit)
+    }
+}
+
+/// Budget of a search that both MCTS players understand
+#[derive(Clone, Copy, Debug)]
+pub enum TimeControl {
+    /// number of new simulations
+    Iterations(u64),
+    WallClock(Duration),
+    /// size of the searched tree, including the part reused from earlier moves
+    Nodes(u64),
+}
+
+impl TimeControl {
+    fn is_reached(&self, iterations: u64, nodes: u64, start: Instant) -> bool {
+        match *self {
+            TimeControl::Iterations(limit) => iterations >= limit,
+            TimeControl::WallClock(limit) => start.elapsed() >= limit,
+            TimeControl::Nodes(limit) => nodes >= limit,
+        }
+    }
+
+    /// Share of the budget of one of `threads` searches running side by side
+    /// 
+    /// Iterations and nodes are split, every thread gets the full wall clock time
+    fn per_thread(self, threads: usize) -> TimeControl {
+        let threads = threads.max(1) as u64;
+        match self {
+            TimeControl::Iterations(limit) => TimeControl::Iterations(limit.div_ceil(threads)),
+            TimeControl::WallClock(limit) => TimeControl::WallClock(limit),
+            TimeControl::Nodes(limit) => TimeControl::Nodes(limit.div_ceil(threads)),
+        }
+    }
+
+    /// Like `is_reached`, but reads the wall clock only every `CLOCK_CHECK_INTERVAL` iterations
+    fn is_reached_amortized(&self, iterations: u64, nodes: u64, start: Instant) -> bool {
+        match *self {
+            TimeControl::WallClock(_) if !iterations.is_multiple_of(CLOCK_CHECK_INTERVAL) => false,
+            _ => self.is_reached(iterations, nodes, start),
+        }
+    }
+}
+
+/// Longest time `MonteCarloAsync` waits past its budget for the worker to expand the root
+#[cfg(not(target_arch = "wasm32"))]
+const EXPANSION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Simulations between two reads of the clock in a timed search, `Instant::now` isn't free
+const CLOCK_CHECK_INTERVAL: u64 = 64;
+
+/// Result of a search, the chosen move and how it was found
+#[derive(Clone, Copy, Debug)]
+pub struct SearchResult {
+    pub best_move: MetaMove,
+    pub info: SearchInfo,
+}
+
+/// Throughput of the last search, see `MonteCarloSync::last_search_stats`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchStats {
+    /// time spent on the move, including moving the tree to the new position
+    pub duration: Duration,
+    /// simulations run for the move, summed over all threads
+    pub iterations: u64,
+    pub nodes_per_second: f64,
+}
+
+impl SearchStats {
+    fn new(duration: Duration, iterations: u64) -> Self {
+        let seconds = duration.as_secs_f64();
+        let nodes_per_second = if seconds > 0. { iterations as f64 / seconds } else { 0. };
+        SearchStats { duration, iterations, nodes_per_second }
+    }
+}
+
+/// Statistics of a root move after a search, see `MonteCarloSync::root_report`
+#[derive(Clone, Copy, Debug)]
+pub struct MoveStat {
+    pub mv: MetaMove,
+    pub visits: u32,
+    /// average score of the move for the side to move
+    pub win_rate: f32,
+    /// UCT value the next iteration would have seen
+    pub uct: f64,
+}
+
+/// A searcher that can be swapped at runtime as `Box<dyn Engine>`
+/// 
+/// Unlike `Player` the engine gets an explicit budget for every search,
+/// this is the seam for plugging in external engines
+pub trait Engine {
+    fn search(&mut self, state: &GameState, limits: SearchLimits) -> SearchResult;
+}
+
+/// Tuning knobs of the tree search
+#[derive(Clone, Copy, Debug)]
+struct SearchConfig {
+    /// first play urgency, the UCT value of a child that was never visited
+    fpu: f64,
+    /// UCT exploration constant, near 0 plays greedy and larger values widen the search
+    exploration: f64,
+    /// size limit of the tree, once reached leaves are played out without expanding them
+    max_nodes: Option<u64>,
+    /// progressive widening, a node with n visits has `ceil(widening_c * n^widening_alpha)` children
+    /// 
+    /// Infinite adds all children when the node is expanded
+    widening_c: f64,
+    widening_alpha: f64,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            fpu: f64::MAX, // Try every child once before exploiting any
+            exploration: std::f64::consts::SQRT_2,
+            max_nodes: None,
+            widening_c: f64::INFINITY,
+            widening_alpha: 0.5,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Number of children a node with the visits may have
+    fn widened_children(&self, visit_count: f32) -> usize {
+        let children = (self.widening_c * (visit_count as f64).powf(self.widening_alpha)).ceil();
+        if children >= usize::MAX as f64 { usize::MAX } else { (children as usize).max(1) }
+    }
+
+    /// Whether a node with the children and visits gets another child
+    /// 
+    /// Without widening the first expansion already added every move
+    fn widens(&self, children: usize, visit_count: f32) -> bool {
+        self.widening_c.is_finite() && children < self.widened_children(visit_count)
+    }
+}
+
+/// How the move is picked from the children of the root after the search
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FinalMoveSelection {
+    /// the best win rate, noisy for children with few visits
+    MaxWinRate,
+    /// the most visited child
+    #[default]
+    MaxVisits,
+    /// the best win rate among the children with at least a tenth of the most visits
+    Robust,
+}
+
+/// Strength presets of `MonteCarloSync` for players who don't want to tune the search
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Expert,
+}
+
+/// Search settings a `Difficulty` stands for
+struct DifficultyPreset {
+    time_control: TimeControl,
+    exploration: f64,
+    playout_policy: Arc<dyn PlayoutPolicy>,
+}
+
+impl Difficulty {
+    /// The search settings of the difficulty, tuned for `META_DEPTH` 2
+    /// 
+    /// - Easy: 50 iterations and a wide search, random playouts. Misses most
+    ///   sub board threats and is beaten by anyone who plays for lines.
+    /// - Medium: 400 iterations with the usual exploration of sqrt(2), random playouts.
+    ///   Sees immediate sub board wins and blocks.
+    /// - Hard: 1500 iterations and greedy playouts that take sub board wins 80% of
+    ///   the time, so the playouts look like real games.
+    /// - Expert: 6000 iterations, greedy playouts and a lower exploration that
+    ///   spends the budget on the best lines.
+    /// 
+    /// Iterations instead of a time budget keep the strength independent of the machine.
+    fn preset(self) -> DifficultyPreset {
+        let greedy = || Arc::new(EpsilonGreedyPolicy::new(0.2, GreedyPriority::WinFirst));
+        match self {
+            Difficulty::Easy => DifficultyPreset {
+                time_control: TimeControl::Iterations(50),
+                exploration: 2.5,
+                playout_policy: Arc::new(RandomPolicy),
+            },
+            Difficulty::Medium => DifficultyPreset {
+                time_control: TimeControl::Iterations(400),
+                exploration: std::f64::consts::SQRT_2,
+                playout_policy: Arc::new(RandomPolicy),
+            },
+            Difficulty::Hard => DifficultyPreset {
+                time_control: TimeControl::Iterations(1500),
+                exploration: std::f64::consts::SQRT_2,
+                playout_policy: greedy(),
+            },
+            Difficulty::Expert => DifficultyPreset {
+                time_control: TimeControl::Iterations(6000),
+                exploration: 1.0,
+                playout_policy: greedy(),
+            },
+        }
+    }
+}
+
+/// Score and visits of a position, summed over every tree node that reaches it
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct NodeStats {
+    score: f32,
+    visit_count: f32,
+}
+
+impl NodeStats {
+    fn of(knot: &GameTreeKnot) -> Self {
+        NodeStats { score: knot.score, visit_count: knot.visit_count }
+    }
+
+    fn add(&mut self, score: f32) {
+        self.score += score;
+        self.visit_count += 1.;
+    }
+
+    /// Count the pending visits of the node as lost, so other descents prefer other nodes
+    fn with_virtual_loss(mut self, knot: &GameTreeKnot) -> Self {
+        self.visit_count += knot.pending as f32;
+        self
+    }
+}
+
+/// Statistics of the searched positions by `GameState::zobrist_hash`
+type TranspositionTable = HashMap<u64, NodeStats>;
+
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct GameTreeKnot {
+    pub children: Vec<GameTreeKnot>,
+    pub move_: Option<MetaMove>,
+    pub score: f32,
+    pub visit_count: f32,
+    /// nodes in the subtree including this one, kept up to date by the search
+    size: u64,
+    /// virtual loss, the descents that selected the node and didn't backtrack yet
+    /// 
+    /// Every search thread descends its own tree one iteration at a time, so this is 0 whenever
+    /// the children are compared. Groundwork for threads sharing a tree, which would need it atomic.
+    pending: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum MonteCarloAsyncMessage {
+    /// take the tree lock back if paused and move the root down
+    AdvanceMove(MetaMove),
+    /// start a new tree at a position that doesn't continue the worker's game
+    Reset(GameState),
+    /// search the subtree of the expected reply until the next `AdvanceMove`
+    Ponder,
+    /// release the tree lock and send the worker's position on the pause channel
+    Pause,
+    Configure(SearchConfig),
+    Stop,
+}
+
+/// How often the worker predicted the opponent's reply it pondered on
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+struct PonderStats {
+    /// replies that arrived while pondering
+    predictions: AtomicU64,
+    /// replies that were the pondered move
+    hits: AtomicU64,
+}
+
+/// Searches on a worker thread, also while the opponent thinks
+/// 
+/// Not available on `wasm32`, the browser has no threads
+#[cfg(not(target_arch = "wasm32"))]
+pub struct MonteCarloAsync {
+    tree_head: Arc<Mutex<GameTreeKnot>>,
+    /// joined when the player is dropped
+    thread: Option<JoinHandle<()>>,
+    sender: Sender<MonteCarloAsyncMessage>,
+    /// the worker's position for every `Pause`, sent once it released the tree lock
+    paused: Receiver<GameState>,
+    /// position of the worker once it handled the sent messages
+    position: GameState,
+    think_time: Duration,
+    last_search: Option<SearchInfo>,
+    /// simulations run by the worker so far
+    iterations: Arc<AtomicU64>,
+    /// visits of the worker's current root
+    nodes: Arc<AtomicU64>,
+    ponder_stats: Arc<PonderStats>,
+    /// configuration sent to the worker
+    config: SearchConfig,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MonteCarloAsync {
+    pub fn new(think_time: Duration) -> Self {
+        let (sender, receiver) = channel::<MonteCarloAsyncMessage>();
+        let (paused_sender, paused) = channel::<GameState>();
+        let tree_head = Arc::new(Mutex::new(GameTreeKnot::unvisited(None)));
+        
+        let iterations = Arc::new(AtomicU64::new(0));
+        let nodes = Arc::new(AtomicU64::new(0));
+        let ponder_stats = Arc::new(PonderStats::default());
+        
+        MonteCarloAsync {
+            tree_head: Arc::clone(&tree_head),
+            sender,
+            paused,
+            position: GameState::new(),
+            thread: Some(Self::spawn_thread(GameState::new(), tree_head, receiver, paused_sender, Arc::clone(&iterations), Arc::clone(&nodes), Arc::clone(&ponder_stats))),
+            think_time,
+            last_search: None,
+            iterations,
+            nodes,
+            ponder_stats,
+            config: SearchConfig::default(),
+        }
+    }
+
+    fn spawn_thread(
+        game_state: GameState,
+        head: Arc<Mutex<GameTreeKnot>>,
+        receiver: Receiver<MonteCarloAsyncMessage>,
+        paused: Sender<GameState>,
+        iterations: Arc<AtomicU64>,
+        nodes: Arc<AtomicU64>,
+        ponder_stats: Arc<PonderStats>,
+    ) -> JoinHandle<()> {
+        // Named per player to find a worker in a debugger or profiler
+        static WORKER_ID: AtomicU64 = AtomicU64::new(0);
+        let name = format!("mcts-{}", WORKER_ID.fetch_add(1, Ordering::Relaxed));
+
+        thread::Builder::new().name(name).spawn(move || {
+            let mut game_state = game_state;
+            let mut tree_head = Some(head.lock().unwrap());
+            let mut possible_moves = PossibleMoves::new();
+            let mut next_move = MetaMove::new_empty();
+            let mut config = SearchConfig::default();
+            let mut node_count = 1;
+            let mut rng = StdRng::from_entropy();
+            // Set after the own move, the reply is picked once the root has a visited child
+            let mut pondering = false;
+            let mut ponder_move: Option<MetaMove> = None;
+            loop {
+                // While paused there is nothing to search, sleep until the next message
+                let message = if tree_head.is_some() {
+                    match receiver.try_recv() {
+                        Ok(message) => Some(message),
+                        Err(TryRecvError::Empty) => None,
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                } else {
+                    match receiver.recv() {
+                        Ok(message) => Some(message),
+                        Err(_) => return,
+                    }
+                };
+                if let Some(message) = message {
+                    match message {
+                        MonteCarloAsyncMessage::AdvanceMove(move_) => {
+                            if pondering {
+                                ponder_stats.predictions.fetch_add(1, Ordering::Relaxed);
+                                if ponder_move.is_some_and(|ponder_move| ponder_move.absolute_index == move_.absolute_index) {
+                                    ponder_stats.hits.fetch_add(1, Ordering::Relaxed);
+                                }
+                            }
+                            pondering = false;
+                            ponder_move = None;
+                            game_state.set(move_).unwrap();
+                            if tree_head.is_none() {
+                                tree_head = Some(head.lock().unwrap());
+                            }
+                            tree_head.as_mut().unwrap().move_head(move_);
+                            node_count = tree_head.as_ref().unwrap().node_count();
+                            nodes.store(tree_head.as_ref().unwrap().visit_count as u64, Ordering::Relaxed);
+                        }
+                        MonteCarloAsyncMessage::Reset(state) => {
+                            pondering = false;
+                            ponder_move = None;
+                            if tree_head.is_none() {
+                                tree_head = Some(head.lock().unwrap());
+                            }
+                            **tree_head.as_mut().unwrap() = GameTreeKnot::unvisited(state.last_move);
+                            game_state = state;
+                            node_count = 1;
+                            nodes.store(0, Ordering::Relaxed);
+                        }
+                        MonteCarloAsyncMessage::Ponder => {
+                            pondering = true;
+                        }
+                        MonteCarloAsyncMessage::Pause => {
+                            if let Some(tree_head) = tree_head.as_mut() {
+                                tree_head.select_and_backtrack(&mut game_state, &mut possible_moves, &mut next_move, &config, &RandomPolicy, None, true, &mut node_count, &mut rng);
+                            }
+                            tree_head = None;
+                            let _ = paused.send(game_state.clone());
+                        }
+                        MonteCarloAsyncMessage::Configure(new_config) => {
+                            config = new_config;
+                        }
+                        MonteCarloAsyncMessage::Stop => return,
+                    }
+                } else if let Some(tree_head) = tree_head.as_mut(){
+                    if pondering && ponder_move.is_none() {
+                        ponder_move = tree_head.get_most_visited_child().and_then(|child| child.move_);
+                    }
+                    let pondered = ponder_move.and_then(|ponder_move| {
+                        tree_head.select_and_backtrack_child(ponder_move, &mut game_state, &mut possible_moves, &mut next_move, &config, &RandomPolicy, &mut node_count, &mut rng)
+                    });
+                    if pondered.is_none() {
+                        tree_head.select_and_backtrack(&mut game_state, &mut possible_moves, &mut next_move, &config, &RandomPolicy, None, true, &mut node_count, &mut rng);
+                    }
+                    iterations.fetch_add(1, Ordering::Relaxed);
+                    nodes.store(tree_head.visit_count as u64, Ordering::Relaxed);
+                } 
+            }
+        }).expect("Failed to spawn the search thread")
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MonteCarloAsync {
+    /// Set the UCT exploration constant of the worker, see [`SearchConfig::exploration`]
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.config.exploration = exploration;
+        let _ = self.sender.send(MonteCarloAsyncMessage::Configure(self.config));
+        self
+    }
+
+    /// Limit the size of the worker's tree, see [`SearchConfig::max_nodes`]
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.config.max_nodes = Some(max_nodes);
+        let _ = self.sender.send(MonteCarloAsyncMessage::Configure(self.config));
+        self
+    }
+
+    /// Add the children of the worker's nodes gradually, see [`SearchConfig::widening_c`]
+    pub fn with_widening(mut self, widening_c: f64, widening_alpha: f64) -> Self {
+        self.config.widening_c = widening_c;
+        self.config.widening_alpha = widening_alpha;
+        let _ = self.sender.send(MonteCarloAsyncMessage::Configure(self.config));
+        self
+    }
+
+    /// Let the worker search the position until the time control is reached
+    /// 
+    /// The iteration and node counts are published by the worker, so they are
+    /// polled while the worker keeps the tree locked
+    pub fn search_with(&mut self, state: &GameState, time_control: TimeControl) -> SearchResult {
+        self.send_position(state);
+
+        let start = Instant::now();
+        let start_iterations = self.iterations.load(Ordering::Relaxed);
+        while self.thread.as_ref().is_some_and(|thread| !thread.is_finished()) {
+            let iterations = self.iterations.load(Ordering::Relaxed) - start_iterations;
+            if time_control.is_reached(iterations, self.nodes.load(Ordering::Relaxed), start) {
+                break;
+            }
+            match time_control {
+                TimeControl::WallClock(limit) => thread::sleep(limit.saturating_sub(start.elapsed())),
+                _ => thread::sleep(Duration::from_micros(100)),
+            }
+        }
+        // Even a zero budget gets an expanded root. An iteration in progress when the
+        // count was read may still run on the old root, the one after it can't.
+        let wait_start = Instant::now();
+        while self.iterations.load(Ordering::Relaxed) - start_iterations < 2
+            && wait_start.elapsed() < EXPANSION_TIMEOUT
+            && self.thread.as_ref().is_some_and(|thread| !thread.is_finished())
+        {
+            thread::sleep(Duration::from_micros(100));
+        }
+
+        let (tree_head, _) = self.pause();
+        let (best_move, info) = if let Some(best_child) = tree_head.get_best_child_score() {
+            (best_child.move_.unwrap(), tree_head.search_info(best_child))
+        } else {
+            // The worker didn't visit a child in time, fall back to a random legal move
+            let legal_moves = state.legal_moves();
+            let move_ = legal_moves[rand::thread_rng().gen_range(0..legal_moves.len())];
+            (move_, SearchInfo { eval: 0.5, nodes: 0 })
+        };
+        drop(tree_head);
+        self.last_search = Some(info);
+        // Only sent after the guard is dropped, the worker locks the tree again to advance it
+        let _ = self.sender.send(MonteCarloAsyncMessage::AdvanceMove(best_move));
+        self.position.set(best_move).unwrap();
+        let _ = self.sender.send(MonteCarloAsyncMessage::Ponder);
+        SearchResult { best_move, info }
+    }
+
+    /// Bring the worker to the position, every move is sent exactly once
+    /// 
+    /// The moves played since the worker's position are sent one by one. A position
+    /// that doesn't continue the worker's game, like a new game or one after an undo,
+    /// starts a new tree.
+    fn send_position(&mut self, state: &GameState) {
+        let board = &mut state.clone();
+        let mut moves = vec![];
+        while board.ply() > self.position.ply() {
+            match board.unmake_move() {
+                Some(meta_move) => moves.push(meta_move),
+                None => break,
+            }
+        }
+
+        if board.ply() == self.position.ply() && *board == self.position {
+            for meta_move in moves.into_iter().rev() {
+                let _ = self.sender.send(MonteCarloAsyncMessage::AdvanceMove(meta_move));
+            }
+        } else {
+            let _ = self.sender.send(MonteCarloAsyncMessage::Reset(state.clone()));
+        }
+        self.position = state.clone();
+    }
+
+    /// Pause the worker and lock its tree
+    /// 
+    /// Waits until the worker acknowledged the pause, so it never waits for the lock
+    /// while the guard is held. Drop the guard before sending `AdvanceMove` or `Reset`.
+    /// Also returns the worker's position, `None` if the worker is gone.
+    fn pause(&self) -> (MutexGuard<'_, GameTreeKnot>, Option<GameState>) {
+        let _ = self.sender.send(MonteCarloAsyncMessage::Pause);
+        // Fails only if the worker is gone, its guard is released then as well
+        let position = self.paused.recv().ok();
+        (self.tree_head.lock().unwrap_or_else(|poisoned| poisoned.into_inner()), position)
+    }
+
+    /// Share of the opponent's replies that were the move the worker pondered on,
+    /// `None` before the first reply
+    pub fn ponder_hit_rate(&self) -> Option<f64> {
+        let predictions = self.ponder_stats.predictions.load(Ordering::Relaxed);
+        let hits = self.ponder_stats.hits.load(Ordering::Relaxed);
+        (predictions > 0).then(|| hits as f64 / predictions as f64)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for MonteCarloAsync {
+    /// Stop the worker instead of letting it search until the process exits
+    fn drop(&mut self) {
+        let _ = self.sender.send(MonteCarloAsyncMessage::Stop);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Player for MonteCarloAsync {
+    fn get_move(&mut self, board: GameState) -> MetaMove {
+        self.search_with(&board, TimeControl::WallClock(self.think_time)).best_move
+    }
+
+    fn last_search(&self) -> Option<SearchInfo> {
+        self.last_search
+    }
+}
+
+#[derive(Clone)]
+pub struct MonteCarloSync {
+    tree_head: GameTreeKnot,
+    /// Zobrist hash of the position of `tree_head`, `None` before the first search
+    tree_hash: Option<u64>,
+    /// number of nodes in the tree, see `SearchConfig::max_nodes`
+    node_count: u64,
+    /// when to stop searching for a move
+    time_control: TimeControl,
+    last_search: Option<SearchInfo>,
+    last_search_stats: Option<SearchStats>,
+    /// best move and expected continuation of the last search
+    last_pv: Vec<MetaMove>,
+    /// root moves of the last search, see `root_report`
+    last_root_report: Vec<MoveStat>,
+    /// number of plies at the start of the game where the move is sampled
+    random_opening: usize,
+    /// temperature for sampling the opening moves by visit count
+    temperature: f64,
+    config: SearchConfig,
+    final_move_selection: FinalMoveSelection,
+    playout_policy: Arc<dyn PlayoutPolicy>,
+    /// merges the statistics of positions reached by different move orders
+    transpositions: Option<TranspositionTable>,
+    /// number of independent trees searched in parallel, see `with_threads`
+    threads: usize,
+    /// drives the playouts and the opening sampling
+    rng: StdRng,
+}
+
+impl MonteCarloSync {
+    pub fn new(iterations: i32) -> Self {
+        Self::with_rng(TimeControl::Iterations(iterations.max(0) as u64), StdRng::from_entropy())
+    }
+
+    /// Searches the same tree on every run with the same seed
+    pub fn with_seed(iterations: i32, seed: u64) -> Self {
+        Self::with_rng(TimeControl::Iterations(iterations.max(0) as u64), StdRng::seed_from_u64(seed))
+    }
+
+    /// Searches each move until the wall clock exceeds the budget instead of a fixed number of iterations
+    pub fn with_time(budget: Duration) -> Self {
+        Self::with_rng(TimeControl::WallClock(budget), StdRng::from_entropy())
+    }
+
+    fn with_rng(time_control: TimeControl, rng: StdRng) -> Self {
+        MonteCarloSync {
+            tree_head: GameTreeKnot::unvisited(None),
+            tree_hash: None,
+            node_count: 1,
+            time_control,
+            last_search: None,
+            last_search_stats: None,
+            last_pv: vec![],
+            last_root_report: vec![],
+            random_opening: 0,
+            temperature: 1.,
+            config: SearchConfig::default(),
+            final_move_selection: FinalMoveSelection::default(),
+            playout_policy: Arc::new(RandomPolicy),
+            transpositions: None,
+            threads: 1,
+            rng,
+        }
+    }
+
+    /// Sample the moves of the first `plies` plies of a game from the visit counts
+    /// 
+    /// Diversifies self-play games, a higher temperature flattens the distribution.
+    /// A temperature of 0 or below plays the most visited move, NaN panics.
+    pub fn with_random_opening(mut self, plies: usize, temperature: f64) -> Self {
+        assert!(!temperature.is_nan(), "temperature is NaN");
+        self.random_opening = plies;
+        self.temperature = temperature;
+        self
+    }
+
+    /// Give unvisited children a finite UCT value instead of trying all of them first
+    /// 
+    /// A low value lets the search exploit promising lines early at a high branching factor
+    pub fn with_fpu(mut self, fpu: f64) -> Self {
+        self.config.fpu = fpu;
+        self
+    }
+
+    /// Set the UCT exploration constant
+    /// 
+    /// Values near 0 make the play greedy, larger values widen the search
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.config.exploration = exploration;
+        self
+    }
+
+    /// Pick the moves of the playouts with the policy instead of uniformly at random
+    pub fn with_playout_policy(mut self, policy: impl PlayoutPolicy + 'static) -> Self {
+        self.playout_policy = Arc::new(policy);
+        self
+    }
+
+    /// Share the statistics of a position between all nodes that reach it by a different move order
+    /// 
+    /// Panics with more than one thread, see `with_threads`
+    pub fn with_transpositions(mut self) -> Self {
+        assert!(self.threads == 1, "the transposition table can't be shared between search threads");
+        self.transpositions = Some(TranspositionTable::new());
+        self
+    }
+
+    /// Search `threads` independent trees from the root in parallel and add up their root children
+    /// 
+    /// Every thread runs its share of the iterations with its own RNG. The trees
+    /// are not reused for the next move. Panics with more than one thread if the
+    /// transposition table is on, the threads would each need their own.
+    /// Not available on `wasm32`, the browser has no threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        assert!(threads <= 1 || self.transpositions.is_none(), "the transposition table can't be shared between search threads");
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Stop growing the tree at about `max_nodes` nodes, later simulations only update the statistics
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Self {
+        self.config.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Progressive widening, a node with n visits only has `ceil(widening_c * n^widening_alpha)` children
+    /// 
+    /// New children are picked by the playout policy, so the search focuses on its moves first
+    pub fn with_widening(mut self, widening_c: f64, widening_alpha: f64) -> Self {
+        self.config.widening_c = widening_c;
+        self.config.widening_alpha = widening_alpha;
+        self
+    }
+
+    /// Replace the budget, the exploration and the playout policy with a preset, see `Difficulty::preset`
+    pub fn with_difficulty(mut self, difficulty: Difficulty) -> Self {
+        let preset = difficulty.preset();
+        self.time_control = preset.time_control;
+        self.config.exploration = preset.exploration;
+        self.playout_policy = preset.playout_policy;
+        self
+    }
+
+    /// Choose how the move is picked after the search, the most visited child by default
+    pub fn with_final_move_selection(mut self, selection: FinalMoveSelection) -> Self {
+        self.final_move_selection = selection;
+        self
+    }
+
+    /// Search a position from scratch and return the whole tree without playing a move
+    pub fn analyze(&mut self, state: &GameState) -> GameTreeKnot {
+        let mut tree_head = GameTreeKnot::unvisited(state.last_move);
+        let meta_board = &mut state.clone();
+        let possible_moves = &mut PossibleMoves::new();
+        let next_move = &mut MetaMove::new_empty();
+        let start = Instant::now();
+        let mut iterations = 0;
+        let mut node_count = 1;
+        // At least one iteration, so the root has children
+        loop {
+            tree_head.select_and_backtrack(meta_board, possible_moves, next_move, &self.config, self.playout_policy.as_ref(), self.transpositions.as_mut(), true, &mut node_count, &mut self.rng);
+            iterations += 1;
+            if self.time_control.is_reached_amortized(iterations, tree_head.visit_count as u64, start) {
+                break;
+            }
+        }
+        tree_head
+    }
+
+    /// Search a move and explain the choice in words
+    pub fn explain_move(&mut self, state: GameState) -> (MetaMove, String) {
+        let threat_map = state.threat_map();
+        let best_move = self.get_move(state.clone());
+        let info = self.last_search.unwrap();
+        let board = board_name(&best_move);
+
+        let mut explanation = format!(
+            "played cell {} of board {} (win rate {:.0}% over {} sims)",
+            best_move.cell(),
+            board,
+            info.eval * 100.,
+            info.nodes
+        );
+
+        match threat_map[best_move.cell_index()] {
+            score if score >= 1. => explanation.push_str(&format!(", wins board {}", board)),
+            score if score >= 0.75 => explanation.push_str(&format!(", blocks the opponent in board {}", board)),
+            score if score >= 0.5 => explanation.push_str(&format!(", creates a fork in board {}", board)),
+            _ => {}
+        }
+
+        let mut next_state = state;
+        next_state.set(best_move).unwrap();
+        let next_moves = next_state.legal_moves();
+        if next_moves.is_empty() {
+            explanation.push_str("; ends the game");
+        } else if next_moves.into_iter().all(|move_| move_.board() == next_moves[0].board()) {
+            explanation.push_str(&format!("; sends the opponent to board {}", board_name(&next_moves[0])));
+        } else {
+            explanation.push_str("; lets the opponent play in any open board");
+        }
+
+        let mut pv = vec![];
+        self.tree_head.principal_variation(&mut pv);
+        if !pv.is_empty() {
+            let line = pv.iter().map(|move_| move_.to_string()).collect::<Vec<_>>().join(" ");
+            explanation.push_str(&format!("; main line {}", line));
+        }
+
+        (best_move, explanation)
+    }
+
+    /// Run extra simulations on the tree of a position without choosing a move
+    /// 
+    /// Meant for the opponent's thinking time, the next `get_move` reuses the tree
+    /// if the opponent plays a move from this position
+    pub fn ponder(&mut self, state: &GameState, iterations: u64) {
+        if !self.move_head(state) {
+            self.reset_head(state);
+        }
+
+        let meta_board = &mut state.clone();
+        let possible_moves = &mut PossibleMoves::new();
+        let next_move = &mut MetaMove::new_empty();
+        for _ in 0..iterations {
+            self.tree_head.select_and_backtrack(meta_board, possible_moves, next_move, &self.config, self.playout_policy.as_ref(), self.transpositions.as_mut(), true, &mut self.node_count, &mut self.rng);
+        }
+    }
+
+    /// The played move of the last search followed by the line the engine expects
+    pub fn last_pv(&self) -> Vec<MetaMove> {
+        self.last_pv.clone()
+    }
+
+    /// Every root move of the last search, the most visited first
+    /// 
+    /// Empty before the first search and after playing a decided move
+    pub fn root_report(&self) -> Vec<MoveStat> {
+        self.last_root_report.clone()
+    }
+
+    /// Time and simulations of the last move, `None` before the first move
+    /// 
+    /// A decided move is played without a search and reports zero
+    pub fn last_search_stats(&self) -> Option<SearchStats> {
+        self.last_search_stats
+    }
+
+    /// Move the tree head down to the position, keeping the statistics of the subtree
+    /// 
+    /// The tree usually is at the position after the engine's own move, so the
+    /// opponent's reply is one level down. Up to two moves are followed, in case
+    /// the own move wasn't searched. Returns false if the position is not in the tree.
+    fn move_head(&mut self, state: &GameState) -> bool {
+        let Some(tree_hash) = self.tree_hash else {
+            return false;
+        };
+        let board = &mut state.clone();
+        let mut moves = vec![];
+        while board.zobrist_hash() != tree_hash {
+            if moves.len() == 2 {
+                return false;
+            }
+            match board.unmake_move() {
+                Some(meta_move) => moves.push(meta_move),
+                None => return false,
+            }
+        }
+
+        for meta_move in moves.into_iter().rev() {
+            match self.tree_head.take_child(meta_move) {
+                Some(child) => self.tree_head = child,
+                None => return false,
+            }
+        }
+        self.tree_hash = Some(state.zobrist_hash());
+        self.node_count = self.tree_head.node_count();
+        true
+    }
+
+    /// Start a new tree at the position
+    fn reset_head(&mut self, state: &GameState) {
+        self.tree_head = GameTreeKnot::unvisited(state.last_move);
+        self.tree_hash = Some(state.zobrist_hash());
+        self.node_count = 1;
+    }
+}
+
+/// Dotted index path of the board a move is played in
+fn board_name(meta_move: &MetaMove) -> String {
+    meta_move
+        .board()
+        .iter()
+        .map(|index| index.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+impl MonteCarloSync {
+    /// Search the position until the time control is reached
+    pub fn search_with(&mut self, state: &GameState, time_control: TimeControl) -> SearchResult {
+        let time_control = time_control.per_thread(self.threads);
+        self.run_search(state, |iterations, nodes, start| time_control.is_reached_amortized(iterations, nodes, start))
+    }
+
+    /// Advance the tree to the position, search until `is_reached(iterations, nodes, start)`
+    /// and play the best move in the tree
+    /// 
+    /// With more than one thread every thread checks `is_reached` for its own tree
+    fn run_search(&mut self, state: &GameState, is_reached: impl Fn(u64, u64, Instant) -> bool + Sync) -> SearchResult {
+        let meta_board = &mut state.clone();
+        let start = Instant::now();
+        let mut iterations = 0;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.threads > 1 {
+            self.tree_head = self.search_parallel(state, &is_reached);
+            self.tree_hash = Some(state.zobrist_hash());
+            self.node_count = self.tree_head.node_count();
+            // Every thread searched a fresh tree
+            iterations = self.tree_head.visit_count as u64;
+        }
+        if self.threads == 1 && !self.move_head(meta_board) {
+            self.reset_head(meta_board);
+        }
+
+        let possible_moves = &mut PossibleMoves::new();
+        let next_move = &mut MetaMove::new_empty();
+
+        while self.threads == 1 && !is_reached(iterations, self.tree_head.visit_count as u64, start) {
+            self.tree_head.select_and_backtrack(meta_board, possible_moves, next_move, &self.config, self.playout_policy.as_ref(), self.transpositions.as_mut(), true, &mut self.node_count, &mut self.rng);
+            iterations += 1;
+        }
+        // Without a budget no child was visited, expand the root once to get a random move
+        if self.tree_head.get_best_child_score().is_none() {
+            self.tree_head.select_and_backtrack(meta_board, possible_moves, next_move, &self.config, self.playout_policy.as_ref(), self.transpositions.as_mut(), true, &mut self.node_count, &mut self.rng);
+        }
+
+        let best_move = if meta_board.ply() < self.random_opening {
+            self.tree_head.sample_child(self.temperature, &mut self.rng)
+        } else {
+            self.tree_head.select_final_child(self.final_move_selection)
+        };
+        // if best_move.is_none() {
+        //     return MetaMove::new_empty();
+        // }
+        let info = self.tree_head.search_info(best_move.unwrap());
+        let best_move = best_move.unwrap().move_.unwrap();
+        self.last_search = Some(info);
+        self.last_search_stats = Some(SearchStats::new(start.elapsed(), iterations));
+        self.last_root_report = self.tree_head.move_stats(&self.config);
+        self.tree_head = self.tree_head.take_child(best_move).unwrap();
+        self.node_count = self.tree_head.node_count();
+        self.tree_hash = Some(meta_board.zobrist_hash_after(self.tree_head.move_.unwrap()));
+        self.last_pv = self.tree_head.move_.into_iter().collect();
+        self.tree_head.principal_variation(&mut self.last_pv);
+
+        SearchResult { best_move: self.tree_head.move_.unwrap(), info }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MonteCarloSync {
+    /// Search a fresh tree of the position on every thread and merge the roots
+    fn search_parallel(&mut self, state: &GameState, is_reached: &(impl Fn(u64, u64, Instant) -> bool + Sync)) -> GameTreeKnot {
+        let seeds: Vec<u64> = (0..self.threads).map(|_| self.rng.next_u64()).collect();
+        let config = &self.config;
+        let policy = self.playout_policy.as_ref();
+
+        let trees: Vec<GameTreeKnot> = thread::scope(|scope| {
+            let handles: Vec<_> = seeds
+                .into_iter()
+                .map(|seed| {
+                    let mut meta_board = state.clone();
+                    scope.spawn(move || {
+                        let mut rng = StdRng::seed_from_u64(seed);
+                        let mut tree_head = GameTreeKnot::unvisited(meta_board.last_move);
+                        let possible_moves = &mut PossibleMoves::new();
+                        let next_move = &mut MetaMove::new_empty();
+                        let start = Instant::now();
+                        let mut iterations = 0;
+                        let mut node_count = 1;
+                        while !is_reached(iterations, tree_head.visit_count as u64, start) {
+                            tree_head.select_and_backtrack(&mut meta_board, possible_moves, next_move, config, policy, None, true, &mut node_count, &mut rng);
+                            iterations += 1;
+                        }
+                        tree_head
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        let mut trees = trees.into_iter();
+        let mut merged = trees.next().unwrap();
+        for tree in trees {
+            merged.merge_root(tree);
+        }
+        merged
+    }
+}
+
+impl MonteCarloSync {
+    /// Play a decided root move without searching, see `decided_root_move`
+    fn play_decided_move(&mut self, state: &GameState) -> Option<MetaMove> {
+        let (move_, eval) = decided_root_move(state)?;
+        self.last_search = Some(SearchInfo { eval, nodes: 0 });
+        self.last_search_stats = Some(SearchStats::default());
+        self.last_pv = vec![move_];
+        self.last_root_report.clear();
+        self.tree_head = GameTreeKnot::new(move_);
+        self.node_count = 1;
+        self.tree_hash = Some(state.zobrist_hash_after(move_));
+        Some(move_)
+    }
+}
+
+/// A root move that settles the game one way or the other, with its win rate
+/// 
+/// A move that wins right away is taken. If every move lets the opponent win with
+/// the next move, the search can't help anymore and the move with the fewest winning
+/// replies is the best bet. Otherwise the position needs a search and `None` is returned.
+fn decided_root_move(state: &GameState) -> Option<(MetaMove, f32)> {
+    let player = state.current_player;
+    let board = &mut state.clone();
+    let legal_moves = board.legal_moves();
+    if legal_moves.is_empty() {
+        return None;
+    }
+
+    for &move_ in &legal_moves {
+        board.make_move_unchecked(move_);
+        let winner = board.get_winner();
+        board.unmake_move();
+        if winner == player {
+            return Some((move_, 1.));
+        }
+    }
+
+    let mut best = None;
+    for &move_ in &legal_moves {
+        board.make_move_unchecked(move_);
+        let winning_replies = board
+            .legal_moves()
+            .into_iter()
+            .filter(|&&reply| {
+                board.make_move_unchecked(reply);
+                let winner = board.get_winner();
+                board.unmake_move();
+                winner == player.to_other()
+            })
+            .count();
+        board.unmake_move();
+        if winning_replies == 0 {
+            return None;
+        }
+        if best.is_none_or(|(_, fewest)| winning_replies < fewest) {
+            best = Some((move_, winning_replies));
+        }
+    }
+    best.map(|(move_, _)| (move_, 0.))
+}
+
+impl Player for MonteCarloSync {
+    fn get_move(&mut self, meta_board: GameState) -> MetaMove {
+        if let Some(move_) = self.play_decided_move(&meta_board) {
+            return move_;
+        }
+        self.search_with(&meta_board, self.time_control).best_move
+    }
+
+    fn last_search(&self) -> Option<SearchInfo> {
+        self.last_search
+    }
+}
+
+/// Stops at the iteration or time limit, the depth limit doesn't apply to MCTS
+/// 
+/// Without any limit the engine falls back to its own time control
+impl Engine for MonteCarloSync {
+    fn search(&mut self, state: &GameState, limits: SearchLimits) -> SearchResult {
+        if limits.iterations.is_none() && limits.time.is_none() {
+            return self.search_with(state, self.time_control);
+        }
+        let limits = limits.per_thread(self.threads);
+        self.run_search(state, |iterations, _, start| limits.is_reached(iterations, start))
+    }
+}
+
+impl GameTreeKnot {
+    /// Unvisited leaf of the move
+    fn new(meta_move: MetaMove) -> Self {
+        GameTreeKnot::unvisited(Some(meta_move))
+    }
+
+    /// Unvisited node of the position after the move, `None` if the move isn't known
+    fn unvisited(move_: Option<MetaMove>) -> Self {
+        GameTreeKnot {
+            children: vec![],
+            move_,
+            score: 0.,
+            visit_count: 0.,
+            size: 1,
+            pending: 0,
+        }
+    }
+
+    /// Move the child of the move out of the children without cloning its subtree,
+    /// the order of the remaining children changes
+    /// 
+    /// Moves are compared by index, as moves of the move list and of the
+    /// move generator differ in their internal state
+    fn take_child(&mut self, meta_move: MetaMove) -> Option<GameTreeKnot> {
+        let index = self.children
+            .iter()
+            .position(|child| child.move_.is_some_and(|move_| move_.absolute_index == meta_move.absolute_index))?;
+        Some(self.children.swap_remove(index))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn move_head(&mut self, meta_move: MetaMove) {
+        if let Some(child) = self.take_child(meta_move) {
+            *self = child;
+            return;
+        }
+        *self = GameTreeKnot::new(meta_move);
+    }
+    
+    /// Upper Confidence Bound for Trees (UCT) algorithm
+    fn uct(&self, child: NodeStats, config: &SearchConfig) -> f64 {
+        if child.visit_count == 0. {
+            return config.fpu;
+        }
+        let exploitation = child.score as f64 / child.visit_count as f64;
+        let parent_visits = self.visit_count as f64;
+        let child_visits = child.visit_count as f64;
+        exploitation + config.exploration * (parent_visits.ln() / child_visits).sqrt()
+    }
+
+    /// Add the visits and scores of the root and its children of a tree of the same position
+    /// 
+    /// The children are matched by their move, deeper nodes are dropped
+    #[cfg(not(target_arch = "wasm32"))]
+    fn merge_root(&mut self, other: GameTreeKnot) {
+        self.score += other.score;
+        self.visit_count += other.visit_count;
+        for child in &mut self.children {
+            child.children.clear();
+            child.size = 1;
+        }
+        for other_child in other.children {
+            match self.children.iter_mut().find(|child| child.move_ == other_child.move_) {
+                Some(child) => {
+                    child.score += other_child.score;
+                    child.visit_count += other_child.visit_count;
+                }
+                None => self.children.push(GameTreeKnot { children: vec![], size: 1, ..other_child }),
+            }
+        }
+        self.size = 1 + self.children.len() as u64;
+    }
+
+    /// Follows the most visited children until an unexpanded node
+    pub fn principal_variation(&self, out: &mut Vec<MetaMove>) {
+        let best_child = self.children
+            .iter()
+            .filter(|child| child.visit_count > 0.)
+            .max_by(|a, b| a.visit_count.partial_cmp(&b.visit_count).unwrap_or(std::cmp::Ordering::Equal));
+
+        if let Some(child) = best_child {
+            if let Some(move_) = child.move_ {
+                out.push(move_);
+                child.principal_variation(out);
+            }
+        }
+    }
+
+    /// Samples a visited child with a probability of visit_count^(1 / temperature)
+    /// 
+    /// A temperature of 0 or below is the limit of the distribution, the most visited child
+    fn sample_child(&self, temperature: f64, rng: &mut dyn RngCore) -> Option<&GameTreeKnot> {
+        if temperature <= 0. {
+            return self.get_most_visited_child();
+        }
+        // Relative to the most visits, so a low temperature can't overflow the weights
+        let max_visits = self.get_most_visited_child()?.visit_count as f64;
+        let weights: Vec<f64> = self.children
+            .iter()
+            .map(|child| (child.visit_count as f64 / max_visits).powf(1. / temperature))
+            .collect();
+        let distribution = WeightedIndex::new(&weights).ok()?;
+        Some(&self.children[distribution.sample(rng)])
+    }
+
+    /// Number of nodes in the tree below and including this node
+    /// 
+    /// Tracked by the search instead of counted, so it is cheap enough for every move
+    pub fn node_count(&self) -> u64 {
+        self.size
+    }
+
+    /// `node_count` counted node by node
+    #[cfg(test)]
+    fn count_nodes(&self) -> u64 {
+        1 + self.children.iter().map(GameTreeKnot::count_nodes).sum::<u64>()
+    }
+
+    /// Statistics of the children, the most visited first
+    fn move_stats(&self, config: &SearchConfig) -> Vec<MoveStat> {
+        let mut stats: Vec<MoveStat> = self.children
+            .iter()
+            .map(|child| MoveStat {
+                mv: child.move_.unwrap(),
+                visits: child.visit_count as u32,
+                win_rate: if child.visit_count > 0. { child.score / child.visit_count } else { 0. },
+                uct: self.uct(NodeStats::of(child), config),
+            })
+            .collect();
+        stats.sort_by_key(|stat| std::cmp::Reverse(stat.visits));
+        stats
+    }
+
+    /// Evaluation of the child and the size of the search below this node
+    pub fn search_info(&self, best_child: &GameTreeKnot) -> SearchInfo {
+        SearchInfo {
+            eval: best_child.score / best_child.visit_count,
+            nodes: self.visit_count as u32,
+        }
+    }
+
+    /// The visited child with the most visits
+    pub fn get_most_visited_child(&self) -> Option<&GameTreeKnot> {
+        self.children.iter()
+            .filter(|node| node.visit_count > 0.)
+            .max_by(|a, b| a.visit_count.partial_cmp(&b.visit_count).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// The child to play after the search
+    fn select_final_child(&self, selection: FinalMoveSelection) -> Option<&GameTreeKnot> {
+        match selection {
+            FinalMoveSelection::MaxWinRate => self.get_best_child_score(),
+            FinalMoveSelection::MaxVisits => self.get_most_visited_child(),
+            FinalMoveSelection::Robust => {
+                let min_visits = self.get_most_visited_child()?.visit_count / 10.;
+                self.children.iter()
+                    .filter(|node| node.visit_count > 0. && node.visit_count >= min_visits)
+                    .max_by(|a, b| {
+                        let a_rate = a.score / a.visit_count;
+                        let b_rate = b.score / b.visit_count;
+                        a_rate.partial_cmp(&b_rate).unwrap_or(std::cmp::Ordering::Equal)
+                    })
+            }
+        }
+    }
+
+    /// Returns the child with the best score
+    /// 
+    /// The score is calculated as the number of wins divided by the number of visits
+    pub fn get_best_child_score(&self) -> Option<&GameTreeKnot> {
+        if self.children.is_empty() {
+            return None;
+        }
+        self.children.iter()
+            .filter(|node| node.visit_count > 0.) // Filter out nodes with zero visits
+            .max_by(|a, b| {
+                let a_rate = if a.visit_count > 0. {
+                    a.score as f64 / a.visit_count as f64
+                } else {
+                    0.0
+                };
+                let b_rate = if b.visit_count > 0. {
+                    b.score as f64 / b.visit_count as f64
+                } else {
+                    0.0
+                };
+                a_rate.partial_cmp(&b_rate).unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+
+    /// Recursively selects a child node and backtracks the score
+    /// 
+    /// `is_root` is set for the head of the tree only, it is expanded with one of every set of symmetric moves
+    #[allow(clippy::too_many_arguments)]
+    fn select_and_backtrack(
+        &mut self, 
+        meta_board: &mut GameState, 
+        possible_moves: &mut PossibleMoves, 
+        next_move: &mut MetaMove,
+        config: &SearchConfig,
+        policy: &dyn PlayoutPolicy,
+        mut transpositions: Option<&mut TranspositionTable>,
+        is_root: bool,
+        nodes: &mut u64,
+        rng: &mut impl Rng,
+    ) -> f32 
+        {
+        self.visit_count += 1.;
+        let nodes_before = *nodes;
+
+        if self.children.is_empty() {
+            let score = self.expand_and_playout(meta_board, possible_moves, next_move, config, policy, transpositions, is_root, nodes, rng);
+            self.score += score;
+            self.size += *nodes - nodes_before;
+            return score;
+        }
+
+        if config.widens(self.children.len(), self.visit_count) {
+            self.widen(meta_board, possible_moves, next_move, config, policy, is_root, nodes, rng);
+        }
+
+        // Children reached by other move orders too use the statistics of all of them
+        let child_stats = |child: &GameTreeKnot| match transpositions.as_deref() {
+            Some(table) => table
+                .get(&meta_board.zobrist_hash_after(child.move_.unwrap()))
+                .copied()
+                .unwrap_or(NodeStats::of(child)),
+            None => NodeStats::of(child),
+        }.with_virtual_loss(child);
+        let mut best_child = 0;
+        let mut best_score = self.uct(child_stats(&self.children[0]), config);
+        for (i, child) in self.children.iter().enumerate().skip(1) {
+            let score = self.uct(child_stats(child), config);
+            if score > best_score {
+                best_score = score;
+                best_child = i;
+            }
+        }
+
+        let best_node = &mut self.children[best_child];
+
+        let move_ = best_node.move_.unwrap();
+
+        meta_board.make_move_unchecked(move_);
+        best_node.pending += 1;
+        let child_score = best_node.select_and_backtrack(meta_board, possible_moves, next_move, config, policy, transpositions.as_deref_mut(), false, nodes, rng);
+        best_node.pending -= 1;
+        if let Some(table) = transpositions {
+            table.entry(meta_board.zobrist_hash()).or_default().add(child_score);
+        }
+        let result = discount(1. - child_score, 1);
+        self.score += result;
+        self.size += *nodes - nodes_before;
+
+        meta_board.unmake_move();
+        result
+    }
+
+    /// Like `select_and_backtrack`, but descends into the child of the move instead of selecting one
+    /// 
+    /// Used to ponder on the expected reply, returns `None` if the node has no child for the move
+    #[cfg(not(target_arch = "wasm32"))]
+    #[allow(clippy::too_many_arguments)]
+    fn select_and_backtrack_child(
+        &mut self,
+        meta_move: MetaMove,
+        meta_board: &mut GameState,
+        possible_moves: &mut PossibleMoves,
+        next_move: &mut MetaMove,
+        config: &SearchConfig,
+        policy: &dyn PlayoutPolicy,
+        nodes: &mut u64,
+        rng: &mut impl Rng,
+    ) -> Option<f32> {
+        let child = self.children
+            .iter_mut()
+            .find(|child| child.move_.is_some_and(|move_| move_.absolute_index == meta_move.absolute_index))?;
+        self.visit_count += 1.;
+        let nodes_before = *nodes;
+
+        meta_board.make_move_unchecked(meta_move);
+        child.pending += 1;
+        let child_score = child.select_and_backtrack(meta_board, possible_moves, next_move, config, policy, None, false, nodes, rng);
+        child.pending -= 1;
+        meta_board.unmake_move();
+
+        let result = discount(1. - child_score, 1);
+        self.score += result;
+        self.size += *nodes - nodes_before;
+        Some(result)
+    }
+
+    /// Expands a leaf node and plays out a game with the policy
+    /// 
+    /// Once the tree holds `config.max_nodes` nodes the leaf is played out without expanding it.
+    /// The root is expanded whatever the limit, the search needs its children to pick a move.
+    #[allow(clippy::too_many_arguments)]
+    fn expand_and_playout(
+        &mut self,
+        meta_board: &mut GameState,
+        possible_moves: &mut PossibleMoves,
+        next_move: &mut MetaMove,
+        config: &SearchConfig,
+        policy: &dyn PlayoutPolicy,
+        transpositions: Option<&mut TranspositionTable>,
+        is_root: bool,
+        nodes: &mut u64,
+        rng: &mut impl Rng,
+    ) -> f32 {
+        self.get_moves(meta_board, possible_moves, next_move, is_root);
+
+        let widened = config.widened_children(self.visit_count) < possible_moves.len();
+        let new_nodes = if widened { 1 } else { possible_moves.len() as u64 };
+        let is_full = !is_root && config.max_nodes.is_some_and(|max_nodes| *nodes + new_nodes > max_nodes);
+        if possible_moves.is_empty() || is_full {
+            let (player_marker, plies) = if possible_moves.is_empty() {
+                (meta_board.get_winner(), 0)
+            } else {
+                simulate(meta_board, possible_moves, next_move, policy, rng)
+            };
+            // The player to move here is the opponent of the one the node scores for
+            return discount(1. - result_score(player_marker, meta_board.current_player), plies);
+        }
+
+        let first_move = policy.choose(meta_board, possible_moves, rng);
+        *nodes += new_nodes;
+        if widened {
+            self.children.push(GameTreeKnot::new(first_move));
+        } else {
+            for move_ in possible_moves.into_iter() {
+                self.children.push(GameTreeKnot::new(*move_));
+            }
+        }
+
+        let child = self.children.iter_mut().find(|child| child.move_ == Some(first_move)).unwrap();
+        let child_score = child.playout(meta_board, possible_moves, next_move, policy, rng);
+        if let Some(table) = transpositions {
+            table.entry(meta_board.zobrist_hash_after(first_move)).or_default().add(child_score);
+        }
+        discount(1. - child_score, 1)
+    }
+
+    /// Adds a child for a move the policy picks among the moves without a child yet
+    #[allow(clippy::too_many_arguments)]
+    fn widen(
+        &mut self,
+        meta_board: &GameState,
+        possible_moves: &mut PossibleMoves,
+        next_move: &mut MetaMove,
+        config: &SearchConfig,
+        policy: &dyn PlayoutPolicy,
+        is_root: bool,
+        nodes: &mut u64,
+        rng: &mut impl Rng,
+    ) {
+        if config.max_nodes.is_some_and(|max_nodes| *nodes >= max_nodes) {
+            return;
+        }
+        self.get_moves(meta_board, possible_moves, next_move, is_root);
+        possible_moves.retain(|move_| self.children.iter().all(|child| child.move_.unwrap().absolute_index != move_.absolute_index));
+        if !possible_moves.is_empty() {
+            self.children.push(GameTreeKnot::new(policy.choose(meta_board, possible_moves, rng)));
+            *nodes += 1;
+        }
+    }
+
+    /// The moves to expand the node with, at the root only one of every set of symmetric moves
+    fn get_moves(&self, meta_board: &GameState, possible_moves: &mut PossibleMoves, next_move: &mut MetaMove, is_root: bool) {
+        if is_root {
+            meta_board.canonical_moves(possible_moves);
+        } else {
+            meta_board.get_possible_moves(possible_moves, next_move);
+        }
+    }
+
+    /// Plays out a game with the policy until the end
+    /// 
+    /// The board is restored afterwards by taking back all played moves
+    fn playout(
+        &mut self,
+        meta_board: &mut GameState,
+        possible_moves: &mut PossibleMoves,
+        next_move: &mut MetaMove,
+        policy: &dyn PlayoutPolicy,
+        rng: &mut impl Rng,
+    ) -> f32 {
+        let current_player = meta_board.current_player;
+
+        meta_board.make_move_unchecked(self.move_.unwrap());
+        let (player_marker, plies) = simulate(meta_board, possible_moves, next_move, policy, rng);
+        meta_board.unmake_move();
+        let score = discount(result_score(player_marker, current_player), plies);
+
+        self.visit_count += 1.;
+        self.score += score;
+        score
+    }
+}
+
+/// Score of a finished game for the player, 1 for a win and 0 for a loss
+/// 
+/// A game without moves left and without a winner is `Empty` and a draw as well
+fn result_score(winner: PlayerMarker, player: PlayerMarker) -> f32 {
+    match winner {
+        PlayerMarker::Draw | PlayerMarker::Empty => 0.5,
+        winner if winner == player => 1.,
+        _ => 0.,
+    }
+}
+
+/// Share of the distance to a draw a score keeps per ply until the end of the game
+/// 
+/// Makes faster wins score higher and slower losses score less bad
+const WIN_DISTANCE_DECAY: f32 = 0.99;
+
+/// Moves the score towards a draw for every ply it lies in the future
+fn discount(score: f32, plies: u32) -> f32 {
+    0.5 + (score - 0.5) * WIN_DISTANCE_DECAY.powi(plies as i32)
+}
+
+/// Plays the game to the end with the policy and returns the winner and the number of played moves
+/// 
+/// The board is restored afterwards by taking back all played moves
+fn simulate(
+    meta_board: &mut GameState,
+    possible_moves: &mut PossibleMoves,
+    next_move: &mut MetaMove,
+    policy: &dyn PlayoutPolicy,
+    rng: &mut impl Rng,
+) -> (PlayerMarker, u32) {
+    let ply = meta_board.ply();
+    loop {
+        meta_board.get_possible_moves(possible_moves, next_move);
+        if possible_moves.is_empty() {
+            break;
+        }
+        let move_ = policy.choose(meta_board, possible_moves, rng);
+        meta_board.make_move_unchecked(move_);
+    }
+
+    let player_marker = meta_board.get_winner();
+    let plies = (meta_board.ply() - ply) as u32;
+    while meta_board.ply() > ply {
+        meta_board.unmake_move();
+    }
+    (player_marker, plies)
+}
+
+
+// ##############################
+// # Playout Policy
+// ##############################
+
+/// Chooses the moves played during a playout
+/// 
+/// Shared by the search threads, so it has to be `Send` and `Sync`
+pub trait PlayoutPolicy: Send + Sync {
+    fn choose(&self, state: &GameState, moves: &PossibleMoves, rng: &mut dyn RngCore) -> MetaMove;
+}
+
+/// Plays uniformly random moves
+pub struct RandomPolicy;
+
+impl PlayoutPolicy for RandomPolicy {
+    fn choose(&self, _state: &GameState, moves: &PossibleMoves, rng: &mut dyn RngCore) -> MetaMove {
+        moves[rng.gen_range(0..moves.len())]
+    }
+}
+
+/// Which tactical move the `GreedyPolicy` prefers if both are available
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GreedyPriority {
+    /// Complete an own sub board win before blocking the opponent
+    WinFirst,
+    /// Block the opponent's sub board win before completing an own one
+    BlockFirst,
+}
+
+/// Plays moves that win or block a sub board, random moves otherwise
+pub struct GreedyPolicy {
+    priority: GreedyPriority,
+}
+
+impl GreedyPolicy {
+    pub fn new(priority: GreedyPriority) -> Self {
+        GreedyPolicy { priority }
+    }
+}
+
+impl PlayoutPolicy for GreedyPolicy {
+    fn choose(&self, state: &GameState, moves: &PossibleMoves, rng: &mut dyn RngCore) -> MetaMove {
+        let player = state.current_player;
+        let mut win = None;
+        let mut block = None;
+
+        for &move_ in moves.into_iter() {
+            if win.is_none() && state.wins_sub_board(move_, player) {
+                win = Some(move_);
+            }
+            if block.is_none() && state.wins_sub_board(move_, player.to_other()) {
+                block = Some(move_);
+            }
+        }
+
+        let tactical_move = match self.priority {
+            GreedyPriority::WinFirst => win.or(block),
+            GreedyPriority::BlockFirst => block.or(win),
+        };
+        tactical_move.unwrap_or_else(|| RandomPolicy.choose(state, moves, rng))
+    }
+}
+
+/// Plays the greedy move with probability 1 - epsilon and a random move otherwise
+///
+/// epsilon = 1 is a pure random playout, epsilon = 0 a pure greedy one
+pub struct EpsilonGreedyPolicy {
+    epsilon: f64,
+    greedy: GreedyPolicy,
+}
+
+impl EpsilonGreedyPolicy {
+    /// Panics if epsilon is not finite, finite values are clamped to 0..=1
+    pub fn new(epsilon: f64, priority: GreedyPriority) -> Self {
+        assert!(epsilon.is_finite(), "epsilon has to be finite, got {}", epsilon);
+        EpsilonGreedyPolicy {
+            epsilon: epsilon.clamp(0., 1.),
+            greedy: GreedyPolicy::new(priority),
+        }
+    }
+}
+
+impl PlayoutPolicy for EpsilonGreedyPolicy {
+    fn choose(&self, state: &GameState, moves: &PossibleMoves, rng: &mut dyn RngCore) -> MetaMove {
+        // The extremes don't draw a coin, so they match the plain policies exactly
+        let random = match self.epsilon {
+            e if e >= 1. => true,
+            e if e <= 0. => false,
+            e => rng.gen_bool(e),
+        };
+
+        if random {
+            RandomPolicy.choose(state, moves, rng)
+        } else {
+            self.greedy.choose(state, moves, rng)
+        }
+    }
+}
+
+/// Plays decisive and anti-decisive moves, random moves otherwise
+/// 
+/// In order: a move that wins the game, a move on the cell where the opponent would
+/// win the game, a move that wins a sub board. Moves are checked on the bit boards,
+/// the state is never cloned.
+pub struct TacticalPlayoutPolicy;
+
+impl PlayoutPolicy for TacticalPlayoutPolicy {
+    fn choose(&self, state: &GameState, moves: &PossibleMoves, rng: &mut dyn RngCore) -> MetaMove {
+        let player = state.current_player;
+        let mut block = None;
+        let mut sub_board_win = None;
+
+        for &move_ in moves.into_iter() {
+            if !state.wins_sub_board(move_, player) && !state.wins_sub_board(move_, player.to_other()) {
+                continue;
+            }
+            if state.wins_game(move_, player) {
+                return move_;
+            }
+            if block.is_none() && state.wins_game(move_, player.to_other()) {
+                block = Some(move_);
+            }
+            if sub_board_win.is_none() && state.wins_sub_board(move_, player) {
+                sub_board_win = Some(move_);
+            }
+        }
+
+        block.or(sub_board_win).unwrap_or_else(|| RandomPolicy.choose(state, moves, rng))
+    }
+}
+
+/// Chance of X to win the position according to a fresh search, seeded for reproducible evaluations
+pub fn mcts_evaluation(state: &GameState, iterations: i32, seed: u64) -> f32 {
+    match state.get_winner() {
+        PlayerMarker::X => return 1.,
+        PlayerMarker::O => return 0.,
+        PlayerMarker::Draw => return 0.5,
+        PlayerMarker::Empty if state.legal_moves().is_empty() => return 0.5,
+        PlayerMarker::Empty => {}
+    }
+    let tree_head = MonteCarloSync::with_seed(iterations, seed).analyze(state);
+    let best_child = tree_head.get_best_child_score().unwrap();
+    // The score of a child is the win rate of the player to move
+    let eval = best_child.score / best_child.visit_count;
+    if state.current_player == PlayerMarker::X { eval } else { 1. - eval }
+}
+
+// ##############################
+// # Tests
+// ##############################
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_greedy_policy_priority() {
+    let mut state = GameState::new();
+    // X threatens [0, 2], O threatens [0, 5] and X is sent to board 0
+    for index in [[0, 0], [0, 3], [0, 1], [0, 4], [5, 5], [8, 0]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let moves = state.legal_moves();
+    let rng = &mut rand::thread_rng();
+
+    let win_first = GreedyPolicy::new(GreedyPriority::WinFirst).choose(&state, &moves, rng);
+    assert_eq!(win_first.absolute_index, [0, 2]);
+
+    let block_first = GreedyPolicy::new(GreedyPriority::BlockFirst).choose(&state, &moves, rng);
+    assert_eq!(block_first.absolute_index, [0, 5]);
+}
+
+#[cfg(test)]
+fn playout_moves(policy: &dyn PlayoutPolicy, rng: &mut dyn RngCore) -> Vec<MetaMove> {
+    let mut state = GameState::new();
+    let mut moves = vec![];
+    loop {
+        let possible_moves = state.legal_moves();
+        if possible_moves.is_empty() {
+            return moves;
+        }
+        let move_ = policy.choose(&state, &possible_moves, rng);
+        state.set(move_).unwrap();
+        moves.push(move_);
+    }
+}
+
+/// Variance of the X win rate over repeated batches of playouts from the position
+#[cfg(test)]
+fn playout_variance(state: &GameState, policy: &dyn PlayoutPolicy, rng: &mut StdRng) -> f64 {
+    let (batches, playouts) = (40, 20);
+    let possible_moves = &mut PossibleMoves::new();
+    let next_move = &mut MetaMove::new_empty();
+    let estimates: Vec<f64> = (0..batches)
+        .map(|_| {
+            let score: f32 = (0..playouts)
+                .map(|_| result_score(simulate(&mut state.clone(), possible_moves, next_move, policy, rng).0, PlayerMarker::X))
+                .sum();
+            score as f64 / playouts as f64
+        })
+        .collect();
+    let mean = estimates.iter().sum::<f64>() / batches as f64;
+    estimates.iter().map(|estimate| (estimate - mean).powi(2)).sum::<f64>() / batches as f64
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_tactical_playout_policy() {
+    // X to move wins the game with 8.7
+    let state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    let rng = &mut StdRng::seed_from_u64(3);
+    let decisive = TacticalPlayoutPolicy.choose(&state, &state.legal_moves(), rng);
+    assert_eq!(decisive.absolute_index, [8, 7]);
+
+    // The random playouts often miss the win, so their estimate of the won position is noisy
+    let tactical = playout_variance(&state, &TacticalPlayoutPolicy, rng);
+    let random = playout_variance(&state, &RandomPolicy, rng);
+    assert_eq!(tactical, 0.);
+    assert!(random > 0.);
+
+    // Block the cell where the opponent would win the game
+    let mut blocking = state.clone();
+    blocking.current_player = PlayerMarker::O;
+    let block = TacticalPlayoutPolicy.choose(&blocking, &blocking.legal_moves(), rng);
+    assert_eq!(block.absolute_index, [8, 7]);
+    assert!(!blocking.wins_game(block, PlayerMarker::O));
+}
+
+#[test]
+fn test_epsilon_greedy_policy_extremes() {
+    let random = playout_moves(&RandomPolicy, &mut StdRng::seed_from_u64(42));
+    let epsilon_one = EpsilonGreedyPolicy::new(1., GreedyPriority::WinFirst);
+    assert_eq!(playout_moves(&epsilon_one, &mut StdRng::seed_from_u64(42)), random);
+
+    let greedy = playout_moves(&GreedyPolicy::new(GreedyPriority::WinFirst), &mut StdRng::seed_from_u64(7));
+    let epsilon_zero = EpsilonGreedyPolicy::new(0., GreedyPriority::WinFirst);
+    assert_eq!(playout_moves(&epsilon_zero, &mut StdRng::seed_from_u64(7)), greedy);
+
+    // Out of range values are clamped
+    let epsilon_large = EpsilonGreedyPolicy::new(2., GreedyPriority::WinFirst);
+    assert_eq!(playout_moves(&epsilon_large, &mut StdRng::seed_from_u64(42)), random);
+    let epsilon_negative = EpsilonGreedyPolicy::new(-1., GreedyPriority::WinFirst);
+    assert_eq!(playout_moves(&epsilon_negative, &mut StdRng::seed_from_u64(7)), greedy);
+}
+
+#[test]
+#[should_panic(expected = "epsilon has to be finite")]
+fn test_epsilon_greedy_policy_nan() {
+    EpsilonGreedyPolicy::new(f64::NAN, GreedyPriority::WinFirst);
+}
+
+#[test]
+fn test_explain_move() {
+    let mut player = MonteCarloSync::new(200);
+    let (best_move, explanation) = player.explain_move(GameState::new());
+    assert!(explanation.contains(&format!("of board {}", board_name(&best_move))), "{}", explanation);
+}
+
+#[cfg(test)]
+fn is_legal_move(state: &GameState, meta_move: MetaMove) -> bool {
+    state.legal_moves().into_iter().any(|&move_| move_ == meta_move)
+}
+
+#[test]
+fn test_monte_carlo_sync_zero_iterations() {
+    for iterations in [0, 1] {
+        let mut state = GameState::new();
+        let mut player = MonteCarloSync::new(iterations);
+        for _ in 0..3 {
+            let move_ = player.get_move(state.clone());
+            assert!(is_legal_move(&state, move_));
+            state.set(move_).unwrap();
+            state.set(RandomPlayer::new().get_move(state.clone())).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_monte_carlo_async_zero_think_time() {
+    for think_time in [Duration::ZERO, Duration::from_nanos(1), Duration::from_micros(50)] {
+        let state = GameState::new();
+        let mut player = MonteCarloAsync::new(think_time);
+        let move_ = player.get_move(state.clone());
+        assert!(is_legal_move(&state, move_));
+        // Searched, not the random fallback
+        assert!(player.last_search().unwrap().nodes > 0);
+    }
+}
+
+#[test]
+fn test_random_opening() {
+    let mut first_moves = vec![];
+    for _ in 0..20 {
+        let mut player = MonteCarloSync::new(100).with_random_opening(1, 1.);
+        let move_ = player.get_move(GameState::new());
+        if !first_moves.contains(&move_) {
+            first_moves.push(move_);
+        }
+    }
+    assert!(first_moves.len() > 1);
+}
+
+#[test]
+fn test_sample_child() {
+    // Equal visits, so argmax by score always picks the first child but sampling doesn't
+    let children: Vec<GameTreeKnot> = GameState::new()
+        .legal_moves()
+        .into_iter()
+        .take(3)
+        .zip([9., 1., 1.])
+        .map(|(move_, score)| GameTreeKnot { score, visit_count: 10., ..GameTreeKnot::new(*move_) })
+        .collect();
+    let tree_head = GameTreeKnot { children, visit_count: 30., ..GameTreeKnot::unvisited(None) };
+    assert_eq!(tree_head.get_best_child_score().unwrap().move_, tree_head.children[0].move_);
+
+    let rng = &mut StdRng::seed_from_u64(3);
+    let samples: Vec<MetaMove> = (0..50)
+        .map(|_| tree_head.sample_child(1., rng).unwrap().move_.unwrap())
+        .collect();
+    assert!(samples.iter().any(|&move_| Some(move_) != tree_head.children[0].move_));
+}
+
+#[test]
+fn test_sample_child_temperature() {
+    let children: Vec<GameTreeKnot> = GameState::new()
+        .legal_moves()
+        .into_iter()
+        .take(3)
+        .zip([2., 300., 40.])
+        .map(|(move_, visit_count)| GameTreeKnot { visit_count, ..GameTreeKnot::new(*move_) })
+        .collect();
+    let tree_head = GameTreeKnot { children, visit_count: 342., ..GameTreeKnot::unvisited(None) };
+    let most_visited = tree_head.children[1].move_;
+
+    // Zero picks the most visited child, a tiny temperature can't overflow the weights
+    let rng = &mut StdRng::seed_from_u64(5);
+    for temperature in [0., -1., 1e-3] {
+        assert_eq!(tree_head.sample_child(temperature, rng).unwrap().move_, most_visited);
+    }
+
+    let mut player = MonteCarloSync::with_seed(100, 5).with_random_opening(1, 0.);
+    assert!(GameState::new().is_legal(player.get_move(GameState::new())));
+}
+
+#[test]
+fn test_first_play_urgency() {
+    let mut root_moves = PossibleMoves::new();
+    GameState::new().canonical_moves(&mut root_moves);
+    let visited_children = |config: SearchConfig| {
+        let mut tree_head = GameTreeKnot::unvisited(None);
+        let state = &mut GameState::new();
+        let mut nodes = 1;
+        for _ in 0..root_moves.len() + 20 {
+            tree_head.select_and_backtrack(state, &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &config, &RandomPolicy, None, true, &mut nodes, &mut rand::thread_rng());
+        }
+        assert_eq!(tree_head.children.len(), root_moves.len());
+        assert_eq!(tree_head.node_count(), nodes);
+        tree_head.children.iter().filter(|child| child.visit_count > 0.).count()
+    };
+
+    assert_eq!(visited_children(SearchConfig::default()), root_moves.len());
+    assert!(visited_children(SearchConfig { fpu: 0., ..SearchConfig::default() }) < root_moves.len());
+}
+
+#[test]
+fn test_engine_trait_object() {
+    let mut engines: Vec<Box<dyn Engine>> = vec![
+        Box::new(MonteCarloSync::new(10)),
+        Box::new(MonteCarloSync::new(10)),
+    ];
+    let limits = [SearchLimits::iterations(200), SearchLimits::time(Duration::from_millis(20))];
+
+    let state = GameState::new();
+    for (engine, limits) in engines.iter_mut().zip(limits) {
+        let result = engine.search(&state, limits);
+        assert!(is_legal_move(&state, result.best_move));
+        assert!(result.info.nodes > 10);
+    }
+    // The depth limit doesn't apply to MCTS, the engine uses its own iterations
+    let result = engines[0].search(&GameState::new(), SearchLimits::depth(3));
+    assert!(is_legal_move(&state, result.best_move));
+
+    // Alpha-beta is limited by depth instead
+    let mut alpha_beta: Box<dyn Engine> = Box::new(AlphaBetaPlayer::new(1));
+    let shallow = alpha_beta.search(&state, SearchLimits::depth(1));
+    let deep = alpha_beta.search(&state, SearchLimits::depth(2));
+    assert!(is_legal_move(&state, shallow.best_move));
+    assert!(deep.info.nodes > shallow.info.nodes);
+    assert_eq!(alpha_beta.search(&state, SearchLimits::iterations(5)).info.nodes, shallow.info.nodes);
+}
+
+#[test]
+fn test_engine_limits_per_thread() {
+    // The iterations are shared by the threads, not run by every one of them
+    for threads in [1, 4] {
+        let mut player = MonteCarloSync::with_seed(10, 2).with_threads(threads);
+        let result = player.search(&GameState::new(), SearchLimits::iterations(1000));
+        assert_eq!(result.info.nodes, 1000);
+        assert_eq!(player.last_search_stats().unwrap().iterations, 1000);
+    }
+}
+
+#[test]
+fn test_time_control_sync() {
+    let state = GameState::new();
+
+    let result = MonteCarloSync::new(0).search_with(&state, TimeControl::Iterations(150));
+    assert_eq!(result.info.nodes, 150);
+
+    // The node count includes the subtree that is reused from the last search
+    let mut player = MonteCarloSync::new(0);
+    let result = player.search_with(&state, TimeControl::Nodes(300));
+    assert_eq!(result.info.nodes, 300);
+    let mut state_after = state.clone();
+    state_after.set(result.best_move).unwrap();
+    let reused = player.tree_head.visit_count as u32;
+    let result = player.search_with(&state_after, TimeControl::Nodes(reused as u64 + 10));
+    assert_eq!(result.info.nodes, reused + 10);
+
+    let start = Instant::now();
+    MonteCarloSync::new(0).search_with(&state, TimeControl::WallClock(Duration::from_millis(30)));
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[test]
+fn test_time_control_async() {
+    let state = GameState::new();
+    let time_controls = [
+        TimeControl::Iterations(200),
+        TimeControl::Nodes(200),
+        TimeControl::WallClock(Duration::from_millis(30)),
+    ];
+    for time_control in time_controls {
+        let mut player = MonteCarloAsync::new(Duration::ZERO);
+        let start = Instant::now();
+        let result = player.search_with(&state, time_control);
+        assert!(is_legal_move(&state, result.best_move));
+        match time_control {
+            TimeControl::WallClock(limit) => assert!(start.elapsed() >= limit),
+            // The worker keeps searching until it sees the pause
+            _ => assert!(result.info.nodes >= 200),
+        }
+    }
+}
+
+#[test]
+fn test_ponder() {
+    let mut player = MonteCarloSync::new(100);
+    let mut state = GameState::new();
+    state.set(player.get_move(state.clone())).unwrap();
+
+    let visits = player.tree_head.visit_count;
+    player.ponder(&state, 500);
+    assert_eq!(player.tree_head.visit_count, visits + 500.);
+
+    // The opponent plays the move the engine expects most
+    let reply = player.tree_head.children
+        .iter()
+        .max_by(|a, b| a.visit_count.partial_cmp(&b.visit_count).unwrap())
+        .unwrap()
+        .clone();
+    assert!(reply.visit_count > 0.);
+    state.set(reply.move_.unwrap()).unwrap();
+
+    player.get_move(state);
+    assert_eq!(player.last_search().unwrap().nodes, reply.visit_count as u32 + 100);
+}
+
+#[cfg(test)]
+fn record_from_moves(moves: &[[usize; 2]]) -> GameRecord {
+    let mut state = GameState::new();
+    let moves = moves
+        .iter()
+        .map(|index| {
+            let move_ = MetaMove::new(index);
+            let player = state.current_player;
+            state.set(move_).unwrap();
+            MoveRecord { move_, player, time: Duration::ZERO, search: None }
+        })
+        .collect();
+    GameRecord { moves, starting_player: 1, result: 0 }
+}
+
+#[test]
+fn test_decisive_ply() {
+    // O gives up board 0 and 1, X wins the top row
+    let record = record_from_moves(&[
+        [0, 0], [5, 0], [0, 1], [5, 1], [0, 2], [6, 0], [1, 0], [6, 1], [1, 1],
+        [6, 3], [1, 2], [7, 0], [2, 0], [7, 1], [2, 1], [7, 3], [2, 2],
+    ]);
+    // Heuristic: every won sub board is worth 20%
+    let heuristic = |state: &GameState| {
+        let boards = state.won_sub_boards();
+        let x_boards = boards.iter().filter(|(_, player)| *player == PlayerMarker::X).count() as f32;
+        0.5 + 0.2 * (x_boards - (boards.len() as f32 - x_boards))
+    };
+    // Two boards up after [1, 2], the 11th move
+    assert_eq!(record.decisive_ply(0.85, heuristic), Some((11, PlayerMarker::X)));
+    assert_eq!(record.decisive_ply(0.95, heuristic), Some((17, PlayerMarker::X)));
+
+    // A swing that doesn't last doesn't count
+    let evals = [0.5, 0.9, 0.5, 0.2, 0.9, 0.95, 0.5, 0.9, 0.9, 0.9, 0.95, 0.9, 0.95, 0.9, 0.9, 0.9, 0.9, 1.];
+    let mut ply = 0;
+    let table = |_: &GameState| {
+        ply += 1;
+        evals[ply - 1]
+    };
+    assert_eq!(record.decisive_ply(0.8, table), Some((7, PlayerMarker::X)));
+
+    // Undecided at the end
+    assert_eq!(record.decisive_ply(0.8, |_| 0.5), None);
+}
+
+#[test]
+fn test_mcts_evaluation() {
+    let record = record_from_moves(&[[0, 0], [5, 0], [0, 1], [5, 1], [0, 2], [6, 0], [1, 0], [6, 1], [1, 1], [6, 3], [1, 2]]);
+    let state = record.positions().last().unwrap();
+    // X owns two boards of the top row
+    assert!(mcts_evaluation(&state, 1000, 1) > 0.5);
+    // Decided positions are not searched
+    let mut won = state.clone();
+    for index in [[7, 0], [2, 0], [7, 1], [2, 1], [7, 3], [2, 2]] {
+        won.set(MetaMove::new(&index)).unwrap();
+    }
+    assert_eq!(mcts_evaluation(&won, 0, 1), 1.);
+}
+
+#[test]
+fn test_search_restores_board() {
+    let mut state = GameState::new();
+    for index in [[4, 4], [4, 0], [0, 8]] {
+        state.set(MetaMove::new(&index)).unwrap();
+    }
+    let mut board = state.clone();
+    let mut tree_head = GameTreeKnot::unvisited(state.last_move);
+    let mut nodes = 1;
+    for _ in 0..200 {
+        tree_head.select_and_backtrack(&mut board, &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &SearchConfig::default(), &RandomPolicy, None, true, &mut nodes, &mut rand::thread_rng());
+        assert!(board == state);
+        assert_eq!(board.ply(), state.ply());
+    }
+}
+
+#[test]
+fn test_seeded_search() {
+    let mut state = GameState::new();
+    state.set(MetaMove::new(&[4, 4])).unwrap();
+
+    let mut player1 = MonteCarloSync::with_seed(300, 42);
+    let mut player2 = MonteCarloSync::with_seed(300, 42);
+    assert_eq!(player1.get_move(state.clone()), player2.get_move(state.clone()));
+    let visits = |player: &MonteCarloSync| player.tree_head.children.iter().map(|child| child.visit_count).collect::<Vec<_>>();
+    assert_eq!(visits(&player1), visits(&player2));
+    assert_eq!(player1.tree_head.score, player2.tree_head.score);
+
+    let mut random1 = RandomPlayer::with_seed(7);
+    let mut random2 = RandomPlayer::with_seed(7);
+    for _ in 0..5 {
+        assert_eq!(random1.get_move(state.clone()), random2.get_move(state.clone()));
+    }
+}
+
+#[test]
+fn test_exploration() {
+    // The root only has one move of every set of symmetric moves
+    let mut root_moves = PossibleMoves::new();
+    GameState::new().canonical_moves(&mut root_moves);
+    assert!(root_moves.len() < META_SIZE);
+
+    // A huge constant spreads the visits evenly over the root before going deeper
+    let iterations = root_moves.len() as i32 * 3;
+    let tree_head = MonteCarloSync::with_seed(iterations, 1).with_exploration(1e9).analyze(&GameState::new());
+    assert_eq!(tree_head.children.len(), root_moves.len());
+    assert!(tree_head.children.iter().all(|child| root_moves.into_iter().any(|move_| Some(*move_) == child.move_)));
+    assert!(tree_head.children.iter().all(|child| child.visit_count == 3.));
+
+    let tree_head = MonteCarloSync::with_seed(iterations, 1).with_exploration(0.).analyze(&GameState::new());
+    assert!(tree_head.children.iter().any(|child| child.visit_count > 3.));
+
+    // The worker takes the constant over its message queue
+    let mut player = MonteCarloAsync::new(Duration::from_millis(10)).with_exploration(0.5);
+    assert!(GameState::new().is_legal(player.get_move(GameState::new())));
+}
+
+#[test]
+fn test_root_report() {
+    let mut player = MonteCarloSync::with_seed(300, 1);
+    assert!(player.root_report().is_empty());
+    player.get_move(GameState::new());
+
+    let report = player.root_report();
+    let mut root_moves = PossibleMoves::new();
+    GameState::new().canonical_moves(&mut root_moves);
+    assert_eq!(report.len(), root_moves.len());
+    assert!(report.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+    // The root expands on the first iteration, every iteration visits one root move
+    assert_eq!(report.iter().map(|stat| stat.visits).sum::<u32>(), 300);
+    assert_eq!(report.iter().map(|stat| stat.visits).sum::<u32>(), player.last_search().unwrap().nodes);
+    assert!(report.iter().all(|stat| (0. ..=1.).contains(&stat.win_rate)));
+}
+
+#[test]
+fn test_last_search_stats() {
+    let mut player = MonteCarloSync::with_seed(300, 1);
+    assert!(player.last_search_stats().is_none());
+
+    player.get_move(GameState::new());
+    let stats = player.last_search_stats().unwrap();
+    assert_eq!(stats.iterations, 300);
+    assert!(stats.duration > Duration::ZERO);
+    assert!(stats.nodes_per_second > 0.);
+
+    let mut parallel = MonteCarloSync::with_seed(300, 1).with_threads(2);
+    parallel.get_move(GameState::new());
+    assert_eq!(parallel.last_search_stats().unwrap().iterations, 300);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_last_pv() {
+    assert!(MonteCarloSync::new(100).last_pv().is_empty());
+
+    let mut state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    // Only 8.7 of the moves in board 8 wins the game, search it instead of taking it right away
+    let mut player = MonteCarloSync::with_seed(1000, 1);
+    let best_move = player.search_with(&state, player.time_control).best_move;
+    let pv = player.last_pv();
+    assert_eq!(pv[0].absolute_index, best_move.absolute_index);
+    assert_eq!(pv.last().unwrap().absolute_index, "8.7".parse::<MetaMove>().unwrap().absolute_index);
+    assert!(pv.iter().all(|&move_| state.set(move_).is_ok()));
+    assert!(matches!(state.outcome(), Some(GameOutcome::Win(_))));
+}
+
+
+#[test]
+fn test_final_move_selection() {
+    let knot = |index: usize, score: f32, visit_count: f32| GameTreeKnot {
+        score,
+        visit_count,
+        ..GameTreeKnot::new(MetaMove::new(&[index, 0]))
+    };
+    let tree_head = GameTreeKnot {
+        children: vec![knot(0, 2., 2.), knot(1, 240., 400.), knot(2, 30., 60.), knot(3, 1., 10.)],
+        score: 273.,
+        visit_count: 472.,
+        ..GameTreeKnot::unvisited(None)
+    };
+    let selected = |selection| tree_head.select_final_child(selection).unwrap().move_;
+
+    assert_eq!(selected(FinalMoveSelection::MaxWinRate), tree_head.children[0].move_);
+    assert_eq!(selected(FinalMoveSelection::MaxVisits), tree_head.children[1].move_);
+    assert_eq!(selected(FinalMoveSelection::Robust), tree_head.children[1].move_);
+    assert_eq!(tree_head.get_most_visited_child().unwrap().move_, tree_head.children[1].move_);
+
+    // A well visited child with a better win rate beats the most visited one
+    let tree_head = GameTreeKnot {
+        children: vec![knot(0, 2., 2.), knot(1, 240., 400.), knot(2, 45., 60.)],
+        ..tree_head
+    };
+    assert_eq!(tree_head.select_final_child(FinalMoveSelection::Robust).unwrap().move_, tree_head.children[2].move_);
+}
+
+/// User and system time of a thread of this process in clock ticks
+#[cfg(all(test, target_os = "linux"))]
+fn thread_cpu_ticks(name: &str) -> Option<u64> {
+    for task in fs::read_dir("/proc/self/task").ok()? {
+        let path = task.ok()?.path();
+        if fs::read_to_string(path.join("comm")).ok()?.trim() != name {
+            continue;
+        }
+        let stat = fs::read_to_string(path.join("stat")).ok()?;
+        // The fields after the command name, utime and stime are the 14th and 15th field
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        return Some(fields[11].parse::<u64>().ok()? + fields[12].parse::<u64>().ok()?);
+    }
+    None
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_monte_carlo_async_sleeps_while_paused() {
+    let mut player = MonteCarloAsync::new(Duration::from_millis(10));
+    let name = player.thread.as_ref().unwrap().thread().name().unwrap().to_string();
+    drop(player.pause());
+    thread::sleep(Duration::from_millis(50));
+
+    let paused_ticks = thread_cpu_ticks(&name).unwrap();
+    let paused_iterations = player.iterations.load(Ordering::Relaxed);
+    thread::sleep(Duration::from_millis(300));
+    assert!(thread_cpu_ticks(&name).unwrap() - paused_ticks <= 3);
+    assert_eq!(player.iterations.load(Ordering::Relaxed), paused_iterations);
+
+    // The next move takes the tree back and the worker picks the search up again
+    let state = GameState::from_move_list("4.4").unwrap();
+    player.send_position(&state);
+    thread::sleep(Duration::from_millis(50));
+    assert!(player.iterations.load(Ordering::Relaxed) > paused_iterations);
+    assert!(state.is_legal(player.get_move(state.clone())));
+}
+
+#[test]
+fn test_monte_carlo_async_ponder() {
+    // Nodes below the reply the opponent plays after the worker pondered
+    let reply_after_ponder = |matching: bool| {
+        let mut player = MonteCarloAsync::new(Duration::from_millis(50));
+        let mut state = GameState::new();
+        state.set(player.get_move(state.clone())).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        let (tree_head, _) = player.pause();
+        let predicted = tree_head.get_most_visited_child().unwrap();
+        let reply = if matching {
+            predicted
+        } else {
+            tree_head.children.iter().filter(|child| child.move_ != predicted.move_).max_by_key(|child| child.node_count()).unwrap()
+        };
+        let (reply_move, reply_nodes) = (reply.move_.unwrap(), reply.node_count());
+        drop(tree_head);
+
+        // The reply takes the tree back, like after every move of the opponent
+        state.set(reply_move).unwrap();
+        player.get_move(state);
+        assert_eq!(player.ponder_hit_rate(), Some(if matching { 1. } else { 0. }));
+        reply_nodes
+    };
+    let hit = reply_after_ponder(true);
+    let miss = reply_after_ponder(false);
+    assert!(hit > miss, "{} nodes below the predicted reply, {} below another", hit, miss);
+}
+
+/// Many short async moves in a row, a handshake that can hang shows up as a timeout
+#[test]
+fn test_monte_carlo_async_stress() {
+    let (done, finished) = channel();
+    thread::spawn(move || {
+        for _ in 0..10 {
+            let mut player1 = MonteCarloAsync::new(Duration::ZERO);
+            let mut player2 = MonteCarloAsync::new(Duration::from_micros(20));
+            play_quietly(&mut player1, &mut player2);
+        }
+        done.send(()).unwrap();
+    });
+    finished.recv_timeout(Duration::from_secs(60)).expect("The async players hung or panicked");
+}
+
+#[test]
+fn test_monte_carlo_async_mirrors_game() {
+    let mut player = MonteCarloAsync::new(Duration::from_millis(5));
+    let mut opponent = RandomPlayer::with_seed(3);
+    let mut state = GameState::new();
+    for _ in 0..6 {
+        state.set(player.get_move(state.clone())).unwrap();
+        let (tree_head, position) = player.pause();
+        drop(tree_head);
+        assert!(position.unwrap() == state);
+        // The opponent's move takes the tree back
+        state.set(opponent.get_move(state.clone())).unwrap();
+        player.send_position(&state);
+    }
+
+    // A taken back round and a new game don't continue the worker's game
+    state.undo();
+    state.undo();
+    for mut state in [state, GameState::new()] {
+        state.set(player.get_move(state.clone())).unwrap();
+        let (tree_head, position) = player.pause();
+        drop(tree_head);
+        assert!(position.unwrap() == state);
+    }
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_monte_carlo_async_drop_stops_worker() {
+    let names: Vec<String> = (0..20)
+        .map(|_| {
+            let mut player = MonteCarloAsync::new(Duration::from_millis(1));
+            player.get_move(GameState::new());
+            player.thread.as_ref().unwrap().thread().name().unwrap().to_string()
+        })
+        .collect();
+    assert!(names.iter().all(|name| thread_cpu_ticks(name).is_none()));
+}
+
+#[test]
+fn test_time_budget_sync() {
+    let visits = |budget| MonteCarloSync::with_time(budget).analyze(&GameState::new()).visit_count;
+    let short = visits(Duration::from_millis(50));
+    let long = visits(Duration::from_millis(200));
+    assert!(long > 2. * short, "{} visits in 200ms, {} in 50ms", long, short);
+
+    let mut player = MonteCarloSync::with_time(Duration::from_millis(20));
+    let start = Instant::now();
+    assert!(GameState::new().is_legal(player.get_move(GameState::new())));
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
+
+/// Plays a game without printing, the winner or `Draw`
+#[cfg(test)]
+fn play_quietly(player1: &mut dyn Player, player2: &mut dyn Player) -> PlayerMarker {
+    let mut state = GameState::new();
+    for ply in 0.. {
+        if let Some(outcome) = state.outcome() {
+            return match outcome {
+                GameOutcome::Win(winner) => winner,
+                GameOutcome::Draw => PlayerMarker::Draw,
+            };
+        }
+        let move_ = if ply % 2 == 0 { player1.get_move(state.clone()) } else { player2.get_move(state.clone()) };
+        state.set(move_).unwrap();
+    }
+    unreachable!()
+}
+
+/// Greedy playouts against uniform ones, the gap is only clear over many games,
+/// run with `cargo test --release test_playout_policy_strength -- --ignored`
+#[test]
+#[ignore]
+fn test_playout_policy_strength() {
+    // A small budget, with more iterations both win nearly every game
+    let wins = |greedy: bool| {
+        (0..200u64)
+            .filter(|&seed| {
+                let mut player = MonteCarloSync::with_seed(10, seed);
+                if greedy {
+                    player = player.with_playout_policy(GreedyPolicy::new(GreedyPriority::WinFirst));
+                }
+                let mut random = RandomPlayer::with_seed(seed);
+                // Alternate the side the engine plays
+                if seed % 2 == 0 {
+                    play_quietly(&mut player, &mut random) == PlayerMarker::X
+                } else {
+                    play_quietly(&mut random, &mut player) == PlayerMarker::O
+                }
+            })
+            .count()
+    };
+    let uniform = wins(false);
+    let greedy = wins(true);
+    assert!(greedy > uniform, "greedy playouts won {} games, uniform ones {}", greedy, uniform);
+}
+
+#[test]
+fn test_transpositions() {
+    let state = GameState::from_move_list("4.4").unwrap();
+    let mut player = MonteCarloSync::with_seed(3000, 5).with_transpositions();
+    let tree_head = player.analyze(&state);
+
+    // Sum the visits of all nodes below the root by position
+    fn collect(knot: &GameTreeKnot, state: &mut GameState, visits: &mut HashMap<u64, (f32, usize)>) {
+        for child in &knot.children {
+            state.make_move_unchecked(child.move_.unwrap());
+            let entry = visits.entry(state.zobrist_hash()).or_default();
+            entry.0 += child.visit_count;
+            entry.1 += 1;
+            collect(child, state, visits);
+            state.unmake_move();
+        }
+    }
+    let mut visits = HashMap::new();
+    collect(&tree_head, &mut state.clone(), &mut visits);
+
+    let table = player.transpositions.as_ref().unwrap();
+    for (hash, (visit_count, _)) in &visits {
+        assert_eq!(table.get(hash).map_or(0., |stats| stats.visit_count), *visit_count);
+    }
+    // Some positions were reached by more than one move order
+    assert!(visits.values().any(|&(_, nodes)| nodes > 1));
+}
+
+#[test]
+fn test_max_nodes() {
+    let state = GameState::from_move_list("4.4").unwrap();
+    let mut player = MonteCarloSync::with_seed(5000, 9).with_max_nodes(100);
+    let tree_head = player.analyze(&state);
+    assert!(tree_head.node_count() <= 100);
+    // The simulations past the limit still count
+    assert_eq!(tree_head.visit_count, 5000.);
+
+    assert_eq!(tree_head.node_count(), tree_head.count_nodes());
+
+    // The counter follows the tree across searches
+    player.ponder(&state, 5000);
+    assert_eq!(player.node_count, player.tree_head.count_nodes());
+    assert!(player.node_count <= 100);
+    let mut state = state;
+    state.set(player.get_move(state.clone())).unwrap();
+    assert_eq!(player.node_count, player.tree_head.count_nodes());
+    assert_eq!(player.tree_head.node_count(), player.tree_head.count_nodes());
+    player.ponder(&state, 5000);
+    assert!(player.tree_head.node_count() <= 100);
+
+    // A limit below the children of the root still expands the root
+    for max_nodes in [1, 50] {
+        let mut player = MonteCarloSync::with_seed(200, 1).with_max_nodes(max_nodes);
+        let state = GameState::new();
+        assert!(state.is_legal(player.get_move(state.clone())));
+        assert_eq!(player.last_search().unwrap().nodes, 200);
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_root_parallelization() {
+    let mut tree_head = GameTreeKnot::unvisited(None);
+    let mut other = tree_head.clone();
+    let state = &mut GameState::new();
+    let (mut nodes, mut other_nodes) = (1, 1);
+    for _ in 0..100 {
+        tree_head.select_and_backtrack(state, &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &SearchConfig::default(), &RandomPolicy, None, true, &mut nodes, &mut rand::thread_rng());
+        other.select_and_backtrack(state, &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &SearchConfig::default(), &RandomPolicy, None, true, &mut other_nodes, &mut rand::thread_rng());
+    }
+    tree_head.merge_root(other);
+    assert_eq!(tree_head.visit_count, 200.);
+    let mut root_moves = PossibleMoves::new();
+    state.canonical_moves(&mut root_moves);
+    assert_eq!(tree_head.children.len(), root_moves.len());
+    assert_eq!(tree_head.children.iter().map(|child| child.visit_count).sum::<f32>(), 200.);
+
+    // 4 threads with a quarter of the iterations each find the win in one like a single thread
+    let state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    for threads in [1, 4] {
+        let mut player = MonteCarloSync::with_seed(1000, 7).with_threads(threads);
+        // Search even though the move is decided
+        let result = player.search_with(&state, player.time_control);
+        assert_eq!(result.best_move.to_string(), "8.7");
+        assert_eq!(result.info.nodes, 1000);
+    }
+
+    // Every thread adds its share of the visits to the root moves, the same seed merges the same trees
+    let root_visits = || {
+        let mut player = MonteCarloSync::with_seed(1000, 3).with_threads(4);
+        player.get_move(GameState::new());
+        player.root_report().into_iter().map(|stat| (stat.mv.absolute_index, stat.visits)).collect::<Vec<_>>()
+    };
+    let visits = root_visits();
+    assert_eq!(visits.iter().map(|(_, visits)| visits).sum::<u32>(), 1000);
+    assert_eq!(visits, root_visits());
+
+    // A broken merge plays like a random player, the merged trees beat one
+    let wins = (0..20u64)
+        .filter(|&seed| {
+            let mut player = MonteCarloSync::with_seed(400, seed).with_threads(4);
+            play_quietly(&mut player, &mut RandomPlayer::with_seed(seed)) == PlayerMarker::X
+        })
+        .count();
+    assert!(wins >= 18, "4 threads won {} of 20 games against a random player", wins);
+}
+
+#[test]
+#[should_panic(expected = "transposition table")]
+fn test_threads_with_transpositions() {
+    MonteCarloSync::with_seed(100, 1).with_transpositions().with_threads(2);
+}
+
+#[test]
+fn test_virtual_loss() {
+    let mut tree_head = MonteCarloSync::with_seed(500, 2).analyze(&GameState::new());
+    let mut child = tree_head.get_most_visited_child().unwrap().clone();
+    let config = SearchConfig::default();
+    let uct = |child: &GameTreeKnot| tree_head.uct(NodeStats::of(child).with_virtual_loss(child), &config);
+    let before = uct(&child);
+
+    // A descent through the child makes it less attractive for the next one
+    child.pending += 1;
+    let during = uct(&child);
+    assert!(during < before);
+    child.pending += 1;
+    assert!(uct(&child) < during);
+    child.pending -= 2;
+    assert_eq!(uct(&child), before);
+
+    // Backtracking removes the virtual loss again
+    tree_head.select_and_backtrack(&mut GameState::new(), &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &config, &RandomPolicy, None, true, &mut 1, &mut rand::thread_rng());
+    assert!(tree_head.children.iter().all(|child| child.pending == 0));
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_progressive_widening() {
+    let config = SearchConfig { widening_c: 0.5, widening_alpha: 0.6, ..SearchConfig::default() };
+    assert_eq!(config.widened_children(1.), 1);
+    assert_eq!(config.widened_children(10.), 2);
+    assert_eq!(SearchConfig::default().widened_children(1.), usize::MAX);
+    assert!(config.widens(1, 10.));
+    assert!(!config.widens(2, 10.));
+
+    let tree_head = MonteCarloSync::with_seed(1000, 4).with_widening(0.5, 0.6).analyze(&GameState::new());
+    // 0.5 * 1000^0.6 > 15, the root was visited often enough to have every move up to symmetry
+    let mut root_moves = PossibleMoves::new();
+    GameState::new().canonical_moves(&mut root_moves);
+    assert_eq!(tree_head.children.len(), root_moves.len());
+    for child in &tree_head.children {
+        assert!(child.children.len() <= config.widened_children(child.visit_count));
+    }
+    // Rarely visited children only have a few of their 9 moves
+    let rare = tree_head.children.iter().filter(|child| child.visit_count > 1.).min_by(|a, b| a.visit_count.total_cmp(&b.visit_count)).unwrap();
+    assert!(rare.children.len() < 9, "{} visits, {} children", rare.visit_count, rare.children.len());
+    assert!(tree_head.node_count() < MonteCarloSync::with_seed(1000, 4).analyze(&GameState::new()).node_count());
+}
+
+#[test]
+fn test_default_config_never_widens() {
+    let config = SearchConfig::default();
+    for visit_count in [1., 10., 1e6, f32::MAX] {
+        for children in [1, 15, META_SIZE] {
+            assert!(!config.widens(children, visit_count));
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_terminal_draw_score() {
+    // Every board is full and drawn, nobody won the meta board
+    let state = GameState::from_notation(&format!("{} X -", ["XOXXOOOXO"; 9].join("/"))).unwrap();
+    assert_eq!(state.get_winner(), PlayerMarker::Empty);
+    assert!(state.legal_moves().is_empty());
+
+    let mut leaf = GameTreeKnot::new(MetaMove::new(&[8, 8]));
+    let score = leaf.expand_and_playout(&mut state.clone(), &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &SearchConfig::default(), &RandomPolicy, None, false, &mut 1, &mut rand::thread_rng());
+    assert_eq!(score, 0.5);
+    assert!(leaf.children.is_empty());
+
+    // The playout of the last move ends in the same draw
+    let mut state = GameState::from_notation(&format!("{}/XOXXOOOX_ O -", ["XOXXOOOXO"; 8].join("/"))).unwrap();
+    let mut leaf = GameTreeKnot::new(MetaMove::new(&[8, 8]));
+    let score = leaf.playout(&mut state, &mut PossibleMoves::new(), &mut MetaMove::new_empty(), &RandomPolicy, &mut rand::thread_rng());
+    assert_eq!(score, 0.5);
+    assert_eq!(leaf.score, 0.5);
+
+    assert_eq!(result_score(PlayerMarker::Empty, PlayerMarker::X), 0.5);
+    assert_eq!(result_score(PlayerMarker::Draw, PlayerMarker::O), 0.5);
+    assert_eq!(result_score(PlayerMarker::O, PlayerMarker::O), 1.);
+    assert_eq!(result_score(PlayerMarker::X, PlayerMarker::O), 0.);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_win_distance() {
+    assert!(discount(1., 1) > discount(1., 3));
+    assert!(discount(0., 1) < discount(0., 3));
+    assert_eq!(discount(0.5, 7), 0.5);
+
+    // 8.7 wins right away, 8.6 wins by force two moves of X later
+    let state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    let tree_head = MonteCarloSync::with_seed(2000, 9).analyze(&state);
+    let child = |name: &str| tree_head.children.iter().find(|child| child.move_.unwrap().to_string() == name).unwrap();
+    let (mate_in_1, mate_in_3) = (child("8.7"), child("8.6"));
+    assert!(mate_in_1.score / mate_in_1.visit_count > mate_in_3.score / mate_in_3.visit_count);
+    assert_eq!(tree_head.get_most_visited_child().unwrap().move_.unwrap().to_string(), "8.7");
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_decided_root_move() {
+    // 8.7 completes a line in board 8 and with it the meta board
+    let state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    let mut player = MonteCarloSync::new(1000);
+    assert_eq!(player.get_move(state.clone()).to_string(), "8.7");
+    assert_eq!(player.last_search().unwrap().nodes, 0);
+    assert_eq!(player.tree_head.visit_count, 0.);
+
+    // Positions without a decided move are searched
+    assert!(decided_root_move(&GameState::new()).is_none());
+    let mut player = MonteCarloSync::new(100);
+    player.get_move(GameState::new());
+    assert_eq!(player.last_search().unwrap().nodes, 100);
+}
+
+#[test]
+fn test_tree_reuse() {
+    let mut player = MonteCarloSync::with_seed(300, 1);
+    let mut state = GameState::new();
+    state.set(player.get_move(state.clone())).unwrap();
+
+    // The opponent answers with a move parsed from text
+    let reply = player.tree_head.get_most_visited_child().unwrap().move_.unwrap();
+    state.set(reply.to_string().parse().unwrap()).unwrap();
+    player.get_move(state.clone());
+    assert!(player.last_search().unwrap().nodes > 300);
+
+    // Two plies down when the own move wasn't searched, e.g. after a decided move
+    let mut player = MonteCarloSync::with_seed(300, 2);
+    let mut state = GameState::new();
+    player.ponder(&state, 300);
+    let own_move = player.tree_head.get_most_visited_child().unwrap();
+    let reply = own_move.get_most_visited_child().unwrap();
+    let subtree_visits = reply.visit_count as u32;
+    state.set(own_move.move_.unwrap()).unwrap();
+    state.set(reply.move_.unwrap()).unwrap();
+    player.get_move(state.clone());
+    assert_eq!(player.last_search().unwrap().nodes, subtree_visits + 300);
+
+    // An unrelated position starts a new tree
+    let other = GameState::from_move_list("0.0").unwrap();
+    player.get_move(other);
+    assert_eq!(player.last_search().unwrap().nodes, 300);
+}
+
+/// Advancing the root of a 50k node tree by moving the child out versus cloning it,
+/// run with `cargo test --release bench_advance_head -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn bench_advance_head() {
+    let mut player = MonteCarloSync::with_seed(0, 1);
+    player.ponder(&GameState::new(), 50_000);
+    let tree_head = player.tree_head;
+    let meta_move = tree_head.get_most_visited_child().unwrap().move_.unwrap();
+    let runs = 20;
+
+    let start = Instant::now();
+    for _ in 0..runs {
+        let parent = tree_head.clone();
+        let child = parent.children.iter().find(|child| child.move_ == Some(meta_move)).unwrap().to_owned();
+        drop(parent);
+        std::hint::black_box(child);
+    }
+    let cloned = start.elapsed() / runs;
+
+    let start = Instant::now();
+    for _ in 0..runs {
+        let mut parent = tree_head.clone();
+        let child = parent.take_child(meta_move).unwrap();
+        drop(parent);
+        std::hint::black_box(child);
+    }
+    let taken = start.elapsed() / runs;
+
+    // Both loops clone the whole tree first, subtract that
+    let start = Instant::now();
+    for _ in 0..runs {
+        std::hint::black_box(tree_head.clone());
+    }
+    let setup = start.elapsed() / runs;
+    println!("clone child: {:?}, take child: {:?}", cloned.saturating_sub(setup), taken.saturating_sub(setup));
+}
+
+/// Points of `strong` over 20 seeded games against `weak`, a draw counts half
+#[cfg(test)]
+fn difficulty_match(strong: Difficulty, weak: Difficulty) -> f64 {
+    let (mut seed1, mut seed2) = (0, 1000);
+    let result = run_tournament(
+        || {
+            seed1 += 1;
+            Box::new(MonteCarloSync::with_seed(0, seed1).with_difficulty(strong))
+        },
+        || {
+            seed2 += 1;
+            Box::new(MonteCarloSync::with_seed(0, seed2).with_difficulty(weak))
+        },
+        20,
+    );
+    result.stats.wins1 as f64 + result.stats.draws as f64 / 2.
+}
+
+/// 15 of 20 points is unlikely (p < 0.05) between players of the same strength
+#[cfg(test)]
+const DIFFICULTY_MARGIN: f64 = 15.;
+
+#[test]
+fn test_difficulty() {
+    let player = MonteCarloSync::with_seed(0, 1).with_difficulty(Difficulty::Hard);
+    assert!(matches!(player.time_control, TimeControl::Iterations(1500)));
+    assert_eq!(player.config.exploration, std::f64::consts::SQRT_2);
+
+    assert!(difficulty_match(Difficulty::Medium, Difficulty::Easy) >= DIFFICULTY_MARGIN);
+}
+
+/// The stronger presets take minutes in a debug build
+/// 
+/// run with `cargo test --release test_difficulty_ladder -- --ignored`
+#[test]
+#[ignore]
+fn test_difficulty_ladder() {
+    assert!(difficulty_match(Difficulty::Hard, Difficulty::Medium) >= DIFFICULTY_MARGIN);
+    assert!(difficulty_match(Difficulty::Expert, Difficulty::Hard) >= DIFFICULTY_MARGIN);
+}
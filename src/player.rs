@@ -0,0 +1,598 @@
+use std::{iter, time::{Duration, Instant}};
+#[cfg(test)]
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use colored::Colorize;
+
+use crate::game::{GameOutcome, GameState, InvalidMove, MetaMove, PlayerMarker};
+#[cfg(test)]
+use crate::{mcts::MonteCarloSync, players::RandomPlayer};
+
+// ##############################
+// # Player
+// ##############################
+
+/// Chooses the moves of one side, implement it to plug a bot into `Game`
+/// 
+/// Object safe, `Game` holds its players as `Box<dyn Player>`
+pub trait Player {
+    fn get_move(&mut self, board: GameState) -> MetaMove;
+
+    /// What `Game` asks for, override it to offer more than a move
+    fn get_action(&mut self, board: GameState) -> PlayerAction {
+        PlayerAction::Move(self.get_move(board))
+    }
+
+    /// Info about the last search, only reported by engines
+    fn last_search(&self) -> Option<SearchInfo> {
+        None
+    }
+}
+
+/// Answer of a player whose turn it is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerAction {
+    Move(MetaMove),
+    /// Take back the player's last move and the reply to it, the player moves again
+    /// 
+    /// Ignored before the player has made a move in the game
+    Undo,
+    /// Give up, the other player wins the game
+    Resign,
+}
+
+/// Evaluation and size of an engine's search for a single move
+#[derive(Clone, Copy, Debug)]
+pub struct SearchInfo {
+    /// estimated win rate of the chosen move
+    pub eval: f32,
+    /// number of simulations in the searched tree
+    pub nodes: u32,
+}
+
+// ##############################
+// # Game
+// ##############################
+
+/// Metadata of a single move played in a game
+#[derive(Clone, Debug)]
+pub struct MoveRecord {
+    pub move_: MetaMove,
+    pub player: PlayerMarker,
+    /// time the player needed to choose the move
+    pub time: Duration,
+    /// search info reported by engines
+    pub search: Option<SearchInfo>,
+}
+
+/// Log of a finished game for post-game analysis
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    pub moves: Vec<MoveRecord>,
+    /// 1 if player 1 started, -1 if player 2 started
+    pub starting_player: i8,
+    /// 1 if player 1 wins, -1 if player 2 wins, and 0 if it's a draw
+    pub result: i8,
+}
+
+impl GameRecord {
+    /// Checks if player 1 played the move with the given index
+    pub fn is_player1_move(&self, index: usize) -> bool {
+        index.is_multiple_of(2) == (self.starting_player == 1)
+    }
+
+    /// Replays the game and yields every position, from the empty board to the final one
+    /// 
+    /// Stops early if a recorded move can't be played
+    pub fn positions(&self) -> impl Iterator<Item = GameState> + '_ {
+        let start = GameState::with_starting_player(self.moves.first().map_or(PlayerMarker::X, |first| first.player));
+        iter::once(start.clone()).chain(self.moves.iter().scan(start, |state, move_record| {
+            state.set(move_record.move_).ok()?;
+            Some(state.clone())
+        }))
+    }
+
+    /// Finds the ply after which the game was decided
+    /// 
+    /// `evaluate` returns the chance of X to win a position. The game counts as decided at
+    /// the first position from which on every eval stays at or beyond `threshold` for the
+    /// same player. Returns the number of moves played up to that position and the player.
+    pub fn decisive_ply(&self, threshold: f32, mut evaluate: impl FnMut(&GameState) -> f32) -> Option<(usize, PlayerMarker)> {
+        let evals: Vec<f32> = self.positions().map(|state| evaluate(&state)).collect();
+        let side = |eval: f32| if eval >= threshold {
+            Some(PlayerMarker::X)
+        } else if eval <= 1. - threshold {
+            Some(PlayerMarker::O)
+        } else {
+            None
+        };
+
+        let winner = side(*evals.last()?)?;
+        let undecided = evals.iter().rposition(|&eval| side(eval) != Some(winner));
+        Some((undecided.map_or(0, |ply| ply + 1), winner))
+    }
+}
+
+/// Follows a `Game` as it is played, for logging or a user interface
+pub trait GameObserver {
+    /// Before the first move, with the number of the player that starts
+    fn on_start(&mut self, _starting_player: u8, _state: &GameState) {}
+
+    /// After a move was played, `state` is the position after the move
+    fn on_move(&mut self, player: PlayerMarker, mv: MetaMove, state: &GameState);
+
+    /// The player chose a move that can't be played and has to choose again
+    fn on_invalid_move(&mut self, _player: PlayerMarker, _mv: MetaMove, _state: &GameState) {}
+
+    /// The player took back a round, `state` is the position before their last move
+    fn on_undo(&mut self, _player: PlayerMarker, _state: &GameState) {}
+
+    fn on_finish(&mut self, outcome: GameOutcome);
+}
+
+/// Prints the game to stdout, the default observer of `Game`
+pub struct PrintObserver;
+
+impl GameObserver for PrintObserver {
+    fn on_start(&mut self, starting_player: u8, state: &GameState) {
+        println!("Player {} starts!", starting_player);
+        print!("{}", state.render_highlighted());
+    }
+
+    fn on_move(&mut self, player: PlayerMarker, mv: MetaMove, state: &GameState) {
+        println!("Player {} chose {:?}", player.to_char(), mv.absolute_index);
+        print!("{}", state.render_highlighted());
+    }
+
+    fn on_invalid_move(&mut self, player: PlayerMarker, mv: MetaMove, state: &GameState) {
+        println!("Player {} chose {:?}", player.to_char(), mv.absolute_index);
+        println!("Invalid move!");
+        print!("{}", state.render_highlighted());
+    }
+
+    fn on_undo(&mut self, player: PlayerMarker, state: &GameState) {
+        println!("Player {} took back the last round", player.to_char());
+        print!("{}", state.render_highlighted());
+    }
+
+    fn on_finish(&mut self, outcome: GameOutcome) {
+        match outcome {
+            GameOutcome::Win(player_marker) => {
+                println!("{} wins!", player_marker.to_char());
+                println!("Game over!");
+            }
+            GameOutcome::Draw => println!("{}", "It's a draw!".yellow()),
+        }
+    }
+}
+
+/// A game between two players, the one to start is chosen at random
+/// 
+/// Use `Game::builder` to choose which player plays X and who starts
+pub struct Game {
+    player1: Box<dyn Player>,
+    player2: Box<dyn Player>,
+    board: GameState,
+    starting_player: i8,
+    /// every move applied to the board, in order
+    transcript: Vec<MetaMove>,
+    /// `PrintObserver` unless replaced, `None` plays silently
+    observer: Option<Box<dyn GameObserver>>,
+}
+
+impl Game {
+    pub fn new(player1: Box<dyn Player>, player2: Box<dyn Player>) -> Self {
+        Game {
+            player1,
+            player2,
+            board: GameState::new(),
+            starting_player: if rand::random() { 1 } else { -1 },
+            transcript: vec![],
+            observer: Some(Box::new(PrintObserver)),
+        }
+    }
+
+    /// Report the game to the observer instead of printing it
+    pub fn with_observer(mut self, observer: impl GameObserver + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Play without reporting the game anywhere
+    pub fn without_observer(mut self) -> Self {
+        self.observer = None;
+        self
+    }
+
+    /// Configure the players and the starting side
+    pub fn builder() -> GameBuilder {
+        GameBuilder::default()
+    }
+
+    /// Start from the position instead of the empty board
+    pub fn with_position(mut self, board: GameState) -> Self {
+        self.board = board;
+        self
+    }
+
+    /// The current position
+    pub fn board(&self) -> &GameState {
+        &self.board
+    }
+
+    /// The moves played so far, `replay` turns them back into the position
+    pub fn transcript(&self) -> &[MetaMove] {
+        &self.transcript
+    }
+
+    /// Play the moves from the start position and return the final position
+    /// 
+    /// Fails at the first illegal move with the reason it can't be played
+    pub fn replay(moves: &[MetaMove]) -> Result<GameState, InvalidMove> {
+        let mut state = GameState::new();
+        for &meta_move in moves {
+            state.check_move(meta_move)?;
+            state.set(meta_move)?;
+        }
+        Ok(state)
+    }
+
+    /// Plays the game until a player wins or it's a draw
+    /// 
+    /// Returns the record of all played moves and the result
+    pub fn play(&mut self) -> GameRecord {
+        let mut current_player_index = self.starting_player;
+        let starting_marker = self.board.current_player;
+        let mut moves = vec![];
+        if let Some(observer) = &mut self.observer {
+            observer.on_start(if self.starting_player == 1 { 1 } else { 2 }, &self.board);
+        }
+
+        let result = |outcome| match outcome {
+            GameOutcome::Win(player_marker) if player_marker == starting_marker => self.starting_player,
+            GameOutcome::Win(_) => -self.starting_player,
+            GameOutcome::Draw => 0,
+        };
+        let result = loop {
+            // Checks `can_set` instead of generating the moves, the player generates them anyway
+            if let Some(outcome) = self.board.outcome() {
+                if let Some(observer) = &mut self.observer {
+                    observer.on_finish(outcome);
+                }
+                break result(outcome);
+            }
+
+            let current_player = if current_player_index == 1 {
+                &mut self.player1
+            } else {
+                &mut self.player2
+            };
+
+            let start = Instant::now();
+            let action = current_player.get_action(self.board.clone());
+            let time = start.elapsed();
+            let player = self.board.current_player;
+
+            let chosen_move = match action {
+                PlayerAction::Move(chosen_move) => chosen_move,
+                // Only moves of this game can be taken back, not the ones of `with_position`
+                PlayerAction::Undo if moves.len() >= 2 => {
+                    for _ in 0..2 {
+                        self.board.undo();
+                        self.transcript.pop();
+                        moves.pop();
+                    }
+                    if let Some(observer) = &mut self.observer {
+                        observer.on_undo(player, &self.board);
+                    }
+                    continue;
+                }
+                PlayerAction::Undo => continue,
+                PlayerAction::Resign => {
+                    let outcome = GameOutcome::Win(player.to_other());
+                    if let Some(observer) = &mut self.observer {
+                        observer.on_finish(outcome);
+                    }
+                    break result(outcome);
+                }
+            };
+
+            if self.board.set(chosen_move).is_err() {
+                if let Some(observer) = &mut self.observer {
+                    observer.on_invalid_move(player, chosen_move, &self.board);
+                }
+                continue;
+            }
+            if let Some(observer) = &mut self.observer {
+                observer.on_move(player, chosen_move, &self.board);
+            }
+            self.transcript.push(chosen_move);
+            moves.push(MoveRecord {
+                move_: chosen_move,
+                player,
+                time,
+                search: current_player.last_search(),
+            });
+
+            current_player_index *= -1;
+        };
+
+        GameRecord { moves, starting_player: self.starting_player, result }
+    }
+}
+
+/// Builds a `Game` with fixed sides, player 1 plays X and player 2 plays O
+/// 
+/// Without `starting_player` the side to start is chosen at random on `build`
+#[derive(Default)]
+pub struct GameBuilder {
+    player_x: Option<Box<dyn Player>>,
+    player_o: Option<Box<dyn Player>>,
+    starting_player: Option<PlayerMarker>,
+}
+
+impl GameBuilder {
+    pub fn player_x(mut self, player: Box<dyn Player>) -> Self {
+        self.player_x = Some(player);
+        self
+    }
+
+    pub fn player_o(mut self, player: Box<dyn Player>) -> Self {
+        self.player_o = Some(player);
+        self
+    }
+
+    /// The side that makes the first move
+    pub fn starting_player(mut self, player_marker: PlayerMarker) -> Self {
+        self.starting_player = Some(player_marker);
+        self
+    }
+
+    /// Panics if a player is missing
+    pub fn build(self) -> Game {
+        let starting_player = self.starting_player.unwrap_or_else(|| if rand::random() { PlayerMarker::X } else { PlayerMarker::O });
+        Game {
+            player1: self.player_x.expect("GameBuilder needs a player for X"),
+            player2: self.player_o.expect("GameBuilder needs a player for O"),
+            board: GameState::with_starting_player(starting_player),
+            starting_player: if starting_player == PlayerMarker::X { 1 } else { -1 },
+            transcript: vec![],
+            observer: Some(Box::new(PrintObserver)),
+        }
+    }
+}
+
+// ##############################
+// # Tests
+// ##############################
+
+#[cfg(test)]
+/// Plays the first legal move
+struct FirstMovePlayer;
+
+#[cfg(test)]
+impl Player for FirstMovePlayer {
+    fn get_move(&mut self, board: GameState) -> MetaMove {
+        board.legal_moves()[0]
+    }
+}
+
+/// Plays its actions in order, then the first legal move
+#[cfg(test)]
+struct ScriptedPlayer(VecDeque<PlayerAction>);
+
+#[cfg(test)]
+impl Player for ScriptedPlayer {
+    fn get_move(&mut self, board: GameState) -> MetaMove {
+        board.legal_moves()[0]
+    }
+
+    fn get_action(&mut self, board: GameState) -> PlayerAction {
+        self.0.pop_front().unwrap_or_else(|| PlayerAction::Move(self.get_move(board)))
+    }
+}
+
+/// Counts the rounds taken back, X takes back to the start position
+#[cfg(test)]
+struct UndoCounter(Rc<RefCell<u32>>);
+
+#[cfg(test)]
+impl GameObserver for UndoCounter {
+    fn on_move(&mut self, _player: PlayerMarker, _mv: MetaMove, _state: &GameState) {}
+
+    fn on_finish(&mut self, _outcome: GameOutcome) {}
+
+    fn on_undo(&mut self, player: PlayerMarker, state: &GameState) {
+        assert_eq!(player, PlayerMarker::X);
+        assert!(*state == GameState::new());
+        *self.0.borrow_mut() += 1;
+    }
+}
+
+#[test]
+fn test_game_undo() {
+    let script = [
+        // Nothing to take back yet
+        PlayerAction::Undo,
+        PlayerAction::Move(MetaMove::new(&[4, 4])),
+        PlayerAction::Undo,
+        PlayerAction::Move(MetaMove::new(&[2, 2])),
+    ];
+    let undos = Rc::default();
+
+    let mut game = Game::builder()
+        .player_x(Box::new(ScriptedPlayer(script.into())))
+        .player_o(Box::new(FirstMovePlayer))
+        .starting_player(PlayerMarker::X)
+        .build()
+        .with_observer(UndoCounter(Rc::clone(&undos)));
+    let record = game.play();
+
+    assert_eq!(*undos.borrow(), 1);
+    // 4.4 and the reply are gone, the game went on with 2.2
+    assert_eq!(game.transcript()[0], MetaMove::new(&[2, 2]));
+    assert_eq!(game.transcript()[1].board(), [2]);
+    assert_eq!(record.moves.len(), game.transcript().len());
+    let replay = Game::replay(game.transcript()).unwrap();
+    assert!(replay == *game.board());
+}
+
+#[test]
+fn test_game_resign() {
+    let script = [PlayerAction::Move(MetaMove::new(&[4, 4])), PlayerAction::Resign];
+    let mut game = Game::builder()
+        .player_x(Box::new(FirstMovePlayer))
+        .player_o(Box::new(ScriptedPlayer(script.into())))
+        .starting_player(PlayerMarker::O)
+        .build();
+    let record = game.play();
+
+    // O started and gave up after X's reply, so player 1 wins
+    assert_eq!(record.result, 1);
+    assert_eq!(game.transcript().len(), 2);
+    assert_eq!(record.moves.len(), 2);
+}
+
+#[test]
+fn test_game_builder() {
+    for _ in 0..3 {
+        let mut game = Game::builder()
+            .player_x(Box::new(FirstMovePlayer))
+            .player_o(Box::new(FirstMovePlayer))
+            .starting_player(PlayerMarker::O)
+            .build();
+        assert_eq!(game.board().current_player, PlayerMarker::O);
+
+        let record = game.play();
+        assert_eq!(record.starting_player, -1);
+        assert_eq!(record.moves[0].player, PlayerMarker::O);
+        assert!(!record.is_player1_move(0));
+        assert!(record.positions().last().unwrap() == *game.board());
+        let reparsed = GameState::from_notation(&game.board().to_notation()).unwrap();
+        assert_eq!(game.board().zobrist_hash(), reparsed.zobrist_hash());
+
+        // Player 1 plays X, so the result follows the winning marker
+        let expected = match game.board().outcome() {
+            Some(GameOutcome::Win(PlayerMarker::X)) => 1,
+            Some(GameOutcome::Win(PlayerMarker::O)) => -1,
+            _ => 0,
+        };
+        assert_eq!(record.result, expected);
+    }
+}
+
+#[cfg(test)]
+/// Records the moves and the outcome of a game
+struct RecordingObserver {
+    moves: Rc<RefCell<Vec<MetaMove>>>,
+    outcome: Rc<RefCell<Option<GameOutcome>>>,
+}
+
+#[cfg(test)]
+impl GameObserver for RecordingObserver {
+    fn on_move(&mut self, player: PlayerMarker, mv: MetaMove, state: &GameState) {
+        assert_eq!(state.current_player, player.to_other());
+        self.moves.borrow_mut().push(mv);
+    }
+
+    fn on_finish(&mut self, outcome: GameOutcome) {
+        *self.outcome.borrow_mut() = Some(outcome);
+    }
+}
+
+#[test]
+fn test_game_observer() {
+    let (moves, outcome) = (Rc::default(), Rc::default());
+    let observer = RecordingObserver { moves: Rc::clone(&moves), outcome: Rc::clone(&outcome) };
+    let mut game = Game::new(Box::new(FirstMovePlayer), Box::new(FirstMovePlayer)).with_observer(observer);
+    game.play();
+
+    assert_eq!(*moves.borrow(), game.transcript());
+    assert_eq!(*outcome.borrow(), game.board().outcome());
+}
+
+#[test]
+fn test_game_with_position() {
+    let start = GameState::from_move_list("4.4 4.0").unwrap();
+    let mut game = Game::new(Box::new(FirstMovePlayer), Box::new(FirstMovePlayer)).with_position(start.clone());
+    let record = game.play();
+
+    assert!(game.board().outcome().is_some());
+    assert_eq!(game.transcript().len(), record.moves.len());
+    assert_eq!(game.transcript()[0].board(), [0]);
+    let mut replay = start;
+    for &meta_move in game.transcript() {
+        replay.set(meta_move).unwrap();
+    }
+    assert!(replay == *game.board());
+}
+
+#[test]
+fn test_game_transcript() {
+    let mut game = Game::new(Box::new(RandomPlayer::with_seed(3)), Box::new(RandomPlayer::with_seed(4)));
+    let record = game.play();
+
+    assert_eq!(game.transcript().len(), record.moves.len());
+    let replay = Game::replay(game.transcript()).unwrap();
+    assert!(replay == *game.board());
+    assert_eq!(replay.outcome(), game.board().outcome());
+
+    // The second move has to go to the board the first one sends to
+    let mut moves = game.transcript()[..2].to_vec();
+    moves[1] = MetaMove::new(&[(moves[0].absolute_index[1] + 1) % 9, 0]);
+    assert!(matches!(Game::replay(&moves), Err(InvalidMove::WrongForcedBoard)));
+}
+
+#[test]
+fn test_game_record() {
+    let mut game = Game::new(Box::new(RandomPlayer::new()), Box::new(MonteCarloSync::new(50)));
+    let start = Instant::now();
+    let record = game.play();
+    let elapsed = start.elapsed();
+
+    let mut replay = GameState::new();
+    for move_record in record.moves.iter() {
+        assert!(move_record.player == replay.current_player);
+        replay.set(move_record.move_).unwrap();
+    }
+    assert!(replay == *game.board());
+
+    assert!(record.moves.iter().map(|m| m.time).sum::<Duration>() <= elapsed);
+    for (i, move_record) in record.moves.iter().enumerate() {
+        // player 1 is the random player and reports no search
+        assert_eq!(move_record.search.is_none(), record.is_player1_move(i));
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_game_draw_without_moves() {
+    // Fill every sub board with X O X / X O O / O X X (or the inverse), none of them is won
+    let mut state = GameState::new();
+    for board in 0..9 {
+        for cell in [0, 1, 2, 4, 3, 5, 7, 6, 8] {
+            state.set(MetaMove::new(&[board, cell])).unwrap();
+        }
+    }
+    assert!(state.legal_moves().is_empty());
+    assert_eq!(state.get_winner(), PlayerMarker::Empty);
+
+    let mut game = Game::new(Box::<RandomPlayer>::default(), Box::<RandomPlayer>::default()).with_position(state);
+    let record = game.play();
+    assert_eq!(record.result, 0);
+    assert!(record.moves.is_empty());
+}
+
+#[test]
+fn test_game_record_positions() {
+    let mut game = Game::new(Box::new(RandomPlayer::new()), Box::new(RandomPlayer::new()));
+    let record = game.play();
+
+    let positions: Vec<GameState> = record.positions().collect();
+    assert_eq!(positions.len(), record.moves.len() + 1);
+    assert!(positions[0] == GameState::new());
+    assert!(*positions.last().unwrap() == *game.board());
+    for (position, move_record) in positions[1..].iter().zip(record.moves.iter()) {
+        assert_eq!(position.last_move, Some(move_record.move_));
+    }
+}
@@ -0,0 +1,367 @@
+
+use std::io::{self, BufRead};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{game::{Evaluator, GameOutcome, GameState, HeuristicEvaluator, MetaMove, PossibleMoves}, mcts::{Engine, SearchLimits, SearchResult}, player::{Player, PlayerAction, SearchInfo}};
+#[cfg(test)]
+use crate::{game::{PlayerMarker, META_SIZE}, player::Game};
+
+// ##############################
+// # Random
+// ##############################
+
+/// Plays uniformly random legal moves
+#[derive(Clone)]
+pub struct RandomPlayer {
+    rng: StdRng,
+}
+
+impl RandomPlayer {
+    pub fn new() -> Self {
+        RandomPlayer { rng: StdRng::from_entropy() }
+    }
+
+    /// Plays the same moves on every run with the same seed
+    pub fn with_seed(seed: u64) -> Self {
+        RandomPlayer { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Default for RandomPlayer {
+    fn default() -> Self {
+        RandomPlayer::new()
+    }
+}
+
+impl Player for RandomPlayer {
+    fn get_move(&mut self, board: GameState) -> MetaMove {
+        let possible_moves = board.legal_moves();
+
+        possible_moves[self.rng.gen_range(0..possible_moves.len())]
+    }
+}
+
+// ##############################
+// # Human
+// ##############################
+
+/// Reads the moves from the console
+pub struct HumanPlayer {
+    /// lines fed by a test, stdin if `None`
+    input: Option<Box<dyn BufRead>>,
+}
+
+impl HumanPlayer {
+    pub fn new() -> Self {
+        HumanPlayer { input: None }
+    }
+
+    /// Reads the moves from `input` instead of stdin
+    pub fn with_input(input: impl BufRead + 'static) -> Self {
+        HumanPlayer { input: Some(Box::new(input)) }
+    }
+
+    /// Stdin is only locked while the line is read, so several human players can share it
+    fn read_line(&mut self, line: &mut String) -> io::Result<usize> {
+        match &mut self.input {
+            Some(input) => input.read_line(line),
+            None => io::stdin().lock().read_line(line),
+        }
+    }
+
+    /// Read a move as an index into the listed moves or as coordinates like `7.4`
+    /// 
+    /// Returns `None` for input that is neither or a move that is not possible
+    pub fn parse_move(input: &str, possible_moves: &PossibleMoves) -> Option<MetaMove> {
+        if let Ok(index) = input.parse::<usize>() {
+            return (index < possible_moves.len()).then(|| possible_moves[index]);
+        }
+        let meta_move = MetaMove::from_coords(input).ok()?;
+        possible_moves
+            .into_iter()
+            .find(|move_| move_.absolute_index == meta_move.absolute_index)
+            .copied()
+    }
+}
+
+impl Default for HumanPlayer {
+    fn default() -> Self {
+        HumanPlayer::new()
+    }
+}
+
+impl Player for HumanPlayer {
+    fn get_move(&mut self, board: GameState) -> MetaMove {
+        loop {
+            match self.get_action(board.clone()) {
+                PlayerAction::Move(meta_move) => return meta_move,
+                PlayerAction::Undo => println!("Nothing to take back outside of a game"),
+                // A move is required here, there is no game to give up
+                PlayerAction::Resign => {
+                    println!("The input was closed, playing the first possible move");
+                    return board.legal_moves()[0];
+                }
+            }
+        }
+    }
+
+    /// Reads until the input is a possible move or `undo`, resigns once the input is closed
+    fn get_action(&mut self, board: GameState) -> PlayerAction {
+        let mut input = String::new();
+        let possible_moves = &mut PossibleMoves::new();
+        let next_move = &mut MetaMove::new_empty();
+
+        loop {
+            possible_moves.clear();
+            board.get_possible_moves(possible_moves, next_move);
+
+            for (i, m) in possible_moves.into_iter().enumerate() {
+                println!("{}: {}", i, m);
+            }
+
+            println!("Enter your move (index or coordinates like 7.4), or undo to take back your last move: ");
+            input.clear();
+            match self.read_line(&mut input) {
+                Ok(0) => return PlayerAction::Resign,
+                Ok(_) => {}
+                Err(error) => {
+                    println!("Can't read the input: {}", error);
+                    return PlayerAction::Resign;
+                }
+            }
+            if input.trim() == "undo" {
+                return PlayerAction::Undo;
+            }
+            if let Some(meta_move) = HumanPlayer::parse_move(input.trim(), possible_moves) {
+                return PlayerAction::Move(meta_move);
+            }
+            println!("Invalid move!");
+        }
+    }
+}
+
+// ##############################
+// # Alpha-Beta
+// ##############################
+
+/// Score of a won game, wins found in fewer plies score higher
+const WIN_SCORE: i32 = 1_000_000;
+
+/// Exact negamax search with alpha-beta pruning to a fixed depth
+///
+/// Plays deterministically, the first of several equally good moves is chosen
+pub struct AlphaBetaPlayer {
+    /// plies searched before the position is evaluated statically
+    depth: u8,
+    evaluator: Box<dyn Evaluator>,
+}
+
+impl AlphaBetaPlayer {
+    pub fn new(depth: u8) -> Self {
+        AlphaBetaPlayer { depth, evaluator: Box::new(HeuristicEvaluator) }
+    }
+
+    /// Replace the static evaluation at the depth limit
+    pub fn with_evaluator(mut self, evaluator: impl Evaluator + 'static) -> Self {
+        self.evaluator = Box::new(evaluator);
+        self
+    }
+
+    /// Best move of the side to move and its score, `None` if the game is over
+    pub fn search(&self, state: &mut GameState) -> Option<(MetaMove, i32)> {
+        self.search_to_depth(state, self.depth, &mut 0)
+    }
+
+    /// Like `search` with another depth, counts the searched positions in `nodes`
+    fn search_to_depth(&self, state: &mut GameState, depth: u8, nodes: &mut u32) -> Option<(MetaMove, i32)> {
+        if state.outcome().is_some() {
+            return None;
+        }
+        // Symmetric moves lead to equal positions, one of them is enough
+        let mut possible_moves = PossibleMoves::new();
+        state.canonical_moves(&mut possible_moves);
+
+        let mut best = None;
+        let mut alpha = -WIN_SCORE - 1;
+        for &meta_move in &possible_moves {
+            state.make_move_unchecked(meta_move);
+            let score = -self.negamax(state, depth.saturating_sub(1), 1, -WIN_SCORE - 1, -alpha, nodes);
+            state.unmake_move();
+            if best.is_none() || score > alpha {
+                alpha = score;
+                best = Some((meta_move, score));
+            }
+        }
+        best
+    }
+
+    /// Score of the position from the side to move, `ply` plies below the root
+    fn negamax(&self, state: &mut GameState, depth: u8, ply: i32, mut alpha: i32, beta: i32, nodes: &mut u32) -> i32 {
+        *nodes += 1;
+        match state.outcome() {
+            // The player who just moved won
+            Some(GameOutcome::Win(_)) => return -(WIN_SCORE - ply),
+            Some(GameOutcome::Draw) => return 0,
+            None if depth == 0 => return self.evaluator.evaluate(state),
+            None => {}
+        }
+
+        let mut possible_moves = PossibleMoves::new();
+        state.get_possible_moves(&mut possible_moves, &mut MetaMove::new_empty());
+        for &meta_move in &possible_moves {
+            state.make_move_unchecked(meta_move);
+            let score = -self.negamax(state, depth - 1, ply + 1, -beta, -alpha, nodes);
+            state.unmake_move();
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+}
+
+impl Player for AlphaBetaPlayer {
+    fn get_move(&mut self, mut board: GameState) -> MetaMove {
+        self.search_to_depth(&mut board, self.depth, &mut 0).expect("No move left to search").0
+    }
+}
+
+/// Searches to the depth limit, or to the player's own depth without one
+/// 
+/// A fixed depth search has no iteration or time budget, those limits are ignored
+impl Engine for AlphaBetaPlayer {
+    fn search(&mut self, state: &GameState, limits: SearchLimits) -> SearchResult {
+        let depth = limits.max_depth().map_or(self.depth, |depth| depth.min(u8::MAX as u32) as u8);
+        let mut nodes = 0;
+        let (best_move, score) = self
+            .search_to_depth(&mut state.clone(), depth, &mut nodes)
+            .expect("No move left to search");
+        // Proven results are at the ends, the heuristic scores close to a draw
+        let eval = (0.5 + 0.5 * score as f32 / WIN_SCORE as f32).clamp(0., 1.);
+        SearchResult { best_move, info: SearchInfo { eval, nodes } }
+    }
+}
+
+// ##############################
+// # Tests
+// ##############################
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_alpha_beta_win_in_one() {
+    let mut state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    let (best_move, score) = AlphaBetaPlayer::new(3).search(&mut state).unwrap();
+    assert_eq!(best_move.to_string(), "8.7");
+    assert_eq!(score, WIN_SCORE - 1);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_alpha_beta_win_in_three() {
+    let mut state = GameState::from_move_list(
+        "6.2 2.8 8.1 1.6 6.5 5.1 1.1 1.3 3.6 6.6 6.8 8.4 4.6 5.4 4.2 2.4 4.4 8.3 3.2 2.7 \
+         7.2 2.1 1.8 8.7 7.5 5.7 7.7 7.4 0.7 7.3 3.8 8.0 0.5 3.5 0.1 1.7 7.0"
+    ).unwrap();
+    let player = state.current_player;
+    // No win in one, but a forced one in three plies
+    assert!(AlphaBetaPlayer::new(1).search(&mut state).unwrap().1 < WIN_SCORE - 1);
+    let (best_move, score) = AlphaBetaPlayer::new(3).search(&mut state).unwrap();
+    assert_eq!(score, WIN_SCORE - 3);
+
+    // Every reply of the opponent allows a win
+    state.set(best_move).unwrap();
+    for &reply in &state.legal_moves() {
+        let mut after_reply = state.clone();
+        after_reply.set(reply).unwrap();
+        let (winning_move, _) = AlphaBetaPlayer::new(1).search(&mut after_reply).unwrap();
+        after_reply.set(winning_move).unwrap();
+        assert_eq!(after_reply.outcome(), Some(GameOutcome::Win(player)));
+    }
+
+    // The search leaves the position as it was
+    let before = state.clone();
+    AlphaBetaPlayer::new(3).search(&mut state);
+    assert!(state == before);
+    assert_eq!(state.ply(), before.ply());
+}
+
+#[test]
+fn test_alpha_beta_evaluator() {
+    struct Counter(std::rc::Rc<std::cell::Cell<u32>>);
+    impl Evaluator for Counter {
+        fn evaluate(&self, _: &GameState) -> i32 {
+            self.0.set(self.0.get() + 1);
+            0
+        }
+    }
+
+    let count = std::rc::Rc::new(std::cell::Cell::new(0));
+    let mut player = AlphaBetaPlayer::new(2).with_evaluator(Counter(count.clone()));
+    let state = GameState::from_move_list("4.4").unwrap();
+    assert!(state.is_legal(player.get_move(state.clone())));
+    assert!(count.get() > 0);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_human_parse_move() {
+    let state = GameState::from_move_list("4.4").unwrap();
+    let possible_moves = state.legal_moves();
+
+    assert_eq!(HumanPlayer::parse_move("0", &possible_moves), Some(possible_moves[0]));
+    assert_eq!(HumanPlayer::parse_move("4.0", &possible_moves).map(|m| m.absolute_index), Some([4, 0]));
+    // Valid coordinates, but not in the board the last move sends to
+    assert_eq!(HumanPlayer::parse_move("0.0", &possible_moves), None);
+    // Taken cell
+    assert_eq!(HumanPlayer::parse_move("4.4", &possible_moves), None);
+    assert_eq!(HumanPlayer::parse_move("9", &possible_moves), None);
+    assert_eq!(HumanPlayer::parse_move("4.9", &possible_moves), None);
+    assert_eq!(HumanPlayer::parse_move("four", &possible_moves), None);
+}
+
+#[test]
+fn test_human_undo() {
+    let mut human = HumanPlayer::with_input(io::Cursor::new("nonsense\nundo\n4.4\n"));
+    assert_eq!(human.get_action(GameState::new()), PlayerAction::Undo);
+    let PlayerAction::Move(meta_move) = human.get_action(GameState::new()) else { panic!("Expected a move") };
+    assert_eq!(meta_move.absolute_index, [4, 4]);
+
+    // Play 4.4, take it back with the reply, play 2.2 instead and then always the first move
+    let input = format!("4.4\nundo\n2.2\n{}", "0\n".repeat(META_SIZE));
+    let mut game = Game::builder()
+        .player_x(Box::new(HumanPlayer::with_input(io::Cursor::new(input))))
+        .player_o(Box::new(RandomPlayer::with_seed(1)))
+        .starting_player(PlayerMarker::X)
+        .build()
+        .without_observer();
+    let record = game.play();
+
+    assert_eq!(game.transcript()[0].absolute_index, [2, 2]);
+    assert_eq!(record.moves.len(), game.transcript().len());
+    assert!(game.board().outcome().is_some());
+}
+
+#[test]
+fn test_human_closed_input() {
+    let mut human = HumanPlayer::with_input(io::Cursor::new("4.4\n"));
+    assert!(matches!(human.get_action(GameState::new()), PlayerAction::Move(_)));
+    assert_eq!(human.get_action(GameState::new()), PlayerAction::Resign);
+    // Outside of a game a move is needed anyway
+    assert_eq!(human.get_move(GameState::new()), GameState::new().legal_moves()[0]);
+
+    // Both humans read from closed inputs, X gives up right away
+    let mut game = Game::builder()
+        .player_x(Box::new(HumanPlayer::with_input(io::empty())))
+        .player_o(Box::new(HumanPlayer::with_input(io::empty())))
+        .starting_player(PlayerMarker::X)
+        .build()
+        .without_observer();
+    let record = game.play();
+    assert_eq!(record.result, -1);
+    assert!(record.moves.is_empty());
+}
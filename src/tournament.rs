@@ -0,0 +1,326 @@
+
+use std::{fmt, time::Duration};
+
+use crate::{game::PlayerMarker, player::{Game, GameRecord, Player}};
+
+// ##############################
+// # Match Statistics
+// ##############################
+
+/// Results of all games between two players
+#[derive(Clone, Debug, Default)]
+pub struct MatchStats {
+    pub player1: String,
+    pub player2: String,
+    pub games: u32,
+    pub wins1: u32,
+    pub wins2: u32,
+    pub draws: u32,
+    /// wins of player 1, wins of player 2 and draws in the games player 1 started
+    pub player1_started: [u32; 3],
+    /// wins of player 1, wins of player 2 and draws in the games player 2 started
+    pub player2_started: [u32; 3],
+    /// number of moves of all games
+    pub plies: u32,
+    /// thinking time of player 1 over all games
+    pub time1: Duration,
+    /// thinking time of player 2 over all games
+    pub time2: Duration,
+    /// number of moves of player 1 over all games
+    pub moves1: u32,
+    /// number of moves of player 2 over all games
+    pub moves2: u32,
+}
+
+const CSV_HEADER: &str = "player1,player2,games,wins1,wins2,draws,\
+player1_started_wins1,player1_started_wins2,player1_started_draws,\
+player2_started_wins1,player2_started_wins2,player2_started_draws,\
+average_plies,average_move_ms1,average_move_ms2";
+
+impl MatchStats {
+    pub fn new(player1: &str, player2: &str) -> Self {
+        MatchStats {
+            player1: player1.to_string(),
+            player2: player2.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Adds the result of a played game
+    pub fn add_game(&mut self, record: &GameRecord) {
+        let outcome = match record.result {
+            1 => 0,
+            -1 => 1,
+            _ => 2,
+        };
+        self.games += 1;
+        match outcome {
+            0 => self.wins1 += 1,
+            1 => self.wins2 += 1,
+            _ => self.draws += 1,
+        }
+        if record.starting_player == 1 {
+            self.player1_started[outcome] += 1;
+        } else {
+            self.player2_started[outcome] += 1;
+        }
+
+        self.plies += record.moves.len() as u32;
+        for (i, move_record) in record.moves.iter().enumerate() {
+            if record.is_player1_move(i) {
+                self.time1 += move_record.time;
+                self.moves1 += 1;
+            } else {
+                self.time2 += move_record.time;
+                self.moves2 += 1;
+            }
+        }
+    }
+
+    pub fn average_plies(&self) -> f64 {
+        if self.games == 0 {
+            return 0.;
+        }
+        self.plies as f64 / self.games as f64
+    }
+
+    /// Average thinking time per move of player 1 and player 2 in milliseconds
+    pub fn average_move_ms(&self) -> (f64, f64) {
+        let moves1 = self.moves1.max(1) as f64;
+        let moves2 = self.moves2.max(1) as f64;
+        (
+            self.time1.as_secs_f64() * 1000. / moves1,
+            self.time2.as_secs_f64() * 1000. / moves2,
+        )
+    }
+
+    /// Header and a single row with the statistics of this match
+    pub fn to_csv(&self) -> String {
+        let (ms1, ms2) = self.average_move_ms();
+        format!(
+            "{}\n{},{},{},{},{},{},{},{},{},{},{},{},{:.2},{:.3},{:.3}\n",
+            CSV_HEADER,
+            csv_field(&self.player1),
+            csv_field(&self.player2),
+            self.games,
+            self.wins1,
+            self.wins2,
+            self.draws,
+            self.player1_started[0],
+            self.player1_started[1],
+            self.player1_started[2],
+            self.player2_started[0],
+            self.player2_started[1],
+            self.player2_started[2],
+            self.average_plies(),
+            ms1,
+            ms2,
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        let (ms1, ms2) = self.average_move_ms();
+        format!(
+            "{{\"player1\":{},\"player2\":{},\"games\":{},\"wins1\":{},\"wins2\":{},\"draws\":{},\
+\"player1_started\":{{\"wins1\":{},\"wins2\":{},\"draws\":{}}},\
+\"player2_started\":{{\"wins1\":{},\"wins2\":{},\"draws\":{}}},\
+\"average_plies\":{:.2},\"average_move_ms1\":{:.3},\"average_move_ms2\":{:.3}}}",
+            json_string(&self.player1),
+            json_string(&self.player2),
+            self.games,
+            self.wins1,
+            self.wins2,
+            self.draws,
+            self.player1_started[0],
+            self.player1_started[1],
+            self.player1_started[2],
+            self.player2_started[0],
+            self.player2_started[1],
+            self.player2_started[2],
+            self.average_plies(),
+            ms1,
+            ms2,
+        )
+    }
+}
+
+/// Quotes a CSV field if it contains a separator or a quote
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut result = String::from("\"");
+    for char in value.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+// ##############################
+// # Tournament
+// ##############################
+
+/// Outcome of `run_tournament`, seen from player 1
+#[derive(Clone, Debug)]
+pub struct TournamentResult {
+    pub stats: MatchStats,
+}
+
+impl TournamentResult {
+    /// Share of the games won by player 1
+    pub fn win_rate(&self) -> f64 {
+        if self.stats.games == 0 {
+            return 0.;
+        }
+        self.stats.wins1 as f64 / self.stats.games as f64
+    }
+
+    /// Share of the games won by the side that made the first move
+    pub fn starter_win_rate(&self) -> f64 {
+        if self.stats.games == 0 {
+            return 0.;
+        }
+        (self.stats.player1_started[0] + self.stats.player2_started[1]) as f64 / self.stats.games as f64
+    }
+}
+
+impl fmt::Display for TournamentResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} games | Player 1: {} | Player 2: {} | Draws: {} | Win rate of player 1: {:.1}%",
+            self.stats.games,
+            self.stats.wins1,
+            self.stats.wins2,
+            self.stats.draws,
+            self.win_rate() * 100.
+        )?;
+        let [wins1, wins2, draws] = self.stats.player1_started;
+        write!(f, "\nX started: {} | Player 1: {} | Player 2: {} | Draws: {}", wins1 + wins2 + draws, wins1, wins2, draws)?;
+        let [wins1, wins2, draws] = self.stats.player2_started;
+        write!(f, "\nO started: {} | Player 1: {} | Player 2: {} | Draws: {}", wins1 + wins2 + draws, wins1, wins2, draws)?;
+        write!(f, "\nWin rate of the side to start: {:.1}%", self.starter_win_rate() * 100.)
+    }
+}
+
+/// Plays the number of games between fresh players from the two factories
+/// 
+/// Player 1 plays X and player 2 plays O, the side to start alternates every game
+/// beginning with X
+pub fn run_tournament(
+    mut player1: impl FnMut() -> Box<dyn Player>,
+    mut player2: impl FnMut() -> Box<dyn Player>,
+    games: u32,
+) -> TournamentResult {
+    let mut stats = MatchStats::new("Player 1", "Player 2");
+    for i in 0..games {
+        let mut game = Game::builder()
+            .player_x(player1())
+            .player_o(player2())
+            .starting_player(if i % 2 == 0 { PlayerMarker::X } else { PlayerMarker::O })
+            .build();
+        stats.add_game(&game.play());
+    }
+    TournamentResult { stats }
+}
+
+// ##############################
+// # Tests
+// ##############################
+
+#[cfg(test)]
+fn record(starting_player: i8, result: i8, plies: usize) -> GameRecord {
+    use crate::{game::{MetaMove, PlayerMarker}, player::MoveRecord};
+
+    GameRecord {
+        moves: (0..plies)
+            .map(|_| MoveRecord {
+                move_: MetaMove::new_empty(),
+                player: PlayerMarker::X,
+                time: Duration::from_millis(2),
+                search: None,
+            })
+            .collect(),
+        starting_player,
+        result,
+    }
+}
+
+#[test]
+fn test_match_stats_export() {
+    let mut stats = MatchStats::new("MonteCarloSync(500)", "Random, seeded");
+    stats.add_game(&record(1, 1, 20));
+    stats.add_game(&record(-1, 0, 30));
+
+    assert_eq!(stats.player1_started, [1, 0, 0]);
+    assert_eq!(stats.player2_started, [0, 0, 1]);
+    assert_eq!(stats.average_plies(), 25.);
+
+    let csv = stats.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], CSV_HEADER);
+    assert!(lines[1].starts_with("MonteCarloSync(500),\"Random, seeded\",2,1,0,1,1,0,0,0,0,1,25.00,"));
+    assert_eq!(lines[0].split(',').count(), 15);
+
+    let json = stats.to_json();
+    assert!(json.starts_with("{\"player1\":\"MonteCarloSync(500)\",\"player2\":\"Random, seeded\",\"games\":2,"));
+    assert!(json.ends_with('}'));
+}
+
+#[test]
+fn test_average_move_ms() {
+    // Player 2 starts and makes one move more than player 1
+    let mut game = record(-1, 0, 5);
+    for i in 0..game.moves.len() {
+        game.moves[i].time = Duration::from_millis(if game.is_player1_move(i) { 1 } else { 3 });
+    }
+    let mut stats = MatchStats::new("player 1", "player 2");
+    stats.add_game(&game);
+    assert_eq!((stats.moves1, stats.moves2), (2, 3));
+    let (ms1, ms2) = stats.average_move_ms();
+    assert!((ms1 - 1.).abs() < 1e-9 && (ms2 - 3.).abs() < 1e-9, "{} ms and {} ms", ms1, ms2);
+}
+
+#[test]
+fn test_tournament_alternates_start() {
+    use crate::players::AlphaBetaPlayer;
+
+    let result = run_tournament(|| Box::new(AlphaBetaPlayer::new(1)), || Box::new(AlphaBetaPlayer::new(1)), 4);
+    let stats = &result.stats;
+    assert_eq!(stats.player1_started.iter().sum::<u32>(), 2);
+    assert_eq!(stats.player2_started.iter().sum::<u32>(), 2);
+
+    // Both players play the same deterministic engine, so the starter's result repeats
+    assert_eq!(stats.player1_started, [stats.player2_started[1], stats.player2_started[0], stats.player2_started[2]]);
+    assert_eq!(result.starter_win_rate(), stats.player1_started[0] as f64 / 2.);
+    assert_eq!(result.to_string().lines().count(), 4);
+}
+
+#[test]
+fn test_run_tournament() {
+    use crate::players::RandomPlayer;
+
+    let result = run_tournament(|| Box::new(RandomPlayer::with_seed(5)), || Box::<RandomPlayer>::default(), 6);
+
+    let stats = &result.stats;
+    assert_eq!(stats.games, 6);
+    assert_eq!(stats.wins1 + stats.wins2 + stats.draws, 6);
+    // The side to start alternates
+    assert_eq!(stats.player1_started.iter().sum::<u32>(), 3);
+    assert_eq!(stats.player2_started.iter().sum::<u32>(), 3);
+    assert_eq!(result.win_rate(), stats.wins1 as f64 / 6.);
+    assert!(result.to_string().starts_with("6 games | Player 1: "));
+}
@@ -0,0 +1,180 @@
+//! Small game API for a browser front end
+//!
+//! [`WebGame`] only deals in plain index paths like `[4, 0]` so it maps directly onto
+//! JavaScript arrays. On `wasm32` the `bindings` module exports it through `wasm-bindgen`,
+//! everything else compiles and is tested natively. The engine is a single threaded
+//! [`MonteCarloSync`], threads aren't available in the browser.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{game::{GameState, InvalidMove, MetaMove}, mcts::MonteCarloSync, player::Player};
+#[cfg(test)]
+use crate::game::PlayerMarker;
+
+/// One game driven by a front end, with a built in engine for the computer side
+pub struct WebGame {
+    state: GameState,
+    rng: StdRng,
+}
+
+impl WebGame {
+    /// New game from the start position, the engine is seeded with `seed`
+    pub fn new(seed: u64) -> Self {
+        WebGame::from_state(GameState::new(), seed)
+    }
+
+    /// Continue from an existing position
+    pub fn from_state(state: GameState, seed: u64) -> Self {
+        WebGame { state, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Index paths of all legal moves, empty once the game is over
+    pub fn legal_moves(&self) -> Vec<Vec<usize>> {
+        if self.state.outcome().is_some() {
+            return Vec::new();
+        }
+        self.state.moves().map(|meta_move| meta_move.absolute_index.to_vec()).collect()
+    }
+
+    /// The legal moves as a JSON array of index paths, e.g. `[[4,0],[4,1]]`
+    #[cfg(feature = "serde")]
+    pub fn legal_moves_json(&self) -> String {
+        serde_json::to_string(&self.legal_moves()).expect("index paths always serialize")
+    }
+
+    /// Play the move at `path` for the side to move
+    pub fn apply_move(&mut self, path: &[usize]) -> Result<(), InvalidMove> {
+        let meta_move = MetaMove::try_new(path)?;
+        if self.state.outcome().is_some() {
+            return Err(InvalidMove::BoardDecided);
+        }
+        self.state.check_move(meta_move)?;
+        self.state.set(meta_move)?;
+        Ok(())
+    }
+
+    /// Let the engine pick and play a move, returns its path or `None` if the game is over
+    ///
+    /// Searches `iterations` MCTS iterations, a move that wins right away is always taken
+    pub fn engine_move(&mut self, iterations: u32) -> Option<Vec<usize>> {
+        if self.state.outcome().is_some() {
+            return None;
+        }
+        let iterations = iterations.min(i32::MAX as u32) as i32;
+        let mut engine = MonteCarloSync::with_seed(iterations, self.rng.gen());
+        let meta_move = engine.get_move(self.state.clone());
+        self.state.set(meta_move).ok()?;
+        Some(meta_move.absolute_index.to_vec())
+    }
+}
+
+// ##############################
+// # wasm-bindgen
+// ##############################
+
+#[cfg(target_arch = "wasm32")]
+mod bindings {
+    use wasm_bindgen::prelude::*;
+
+    use super::WebGame;
+
+    /// `WebGame` as seen from JavaScript
+    #[wasm_bindgen(js_name = Game)]
+    pub struct WasmGame(WebGame);
+
+    /// New game with a randomly seeded engine
+    #[wasm_bindgen]
+    pub fn new_game() -> WasmGame {
+        WasmGame(WebGame::new((js_sys::Math::random() * u32::MAX as f64) as u64))
+    }
+
+    #[wasm_bindgen(js_class = Game)]
+    impl WasmGame {
+        /// Array of index paths
+        pub fn legal_moves(&self) -> js_sys::Array {
+            self.0.legal_moves()
+                .into_iter()
+                .map(|path| path.into_iter().map(|index| JsValue::from(index as u32)).collect::<js_sys::Array>())
+                .collect()
+        }
+
+        pub fn apply_move(&mut self, path: Vec<usize>) -> Result<(), JsError> {
+            self.0.apply_move(&path).map_err(|error| JsError::new(&error.to_string()))
+        }
+
+        pub fn engine_move(&mut self, iterations: u32) -> Option<Vec<usize>> {
+            self.0.engine_move(iterations)
+        }
+
+        /// `X`, `O` or `-` while the game is running or drawn
+        pub fn winner(&self) -> String {
+            self.0.state().get_winner().to_char().to_string()
+        }
+
+        pub fn notation(&self) -> String {
+            self.0.state().to_notation()
+        }
+    }
+}
+
+// ##############################
+// # Tests
+// ##############################
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_web_game() {
+    let mut game = WebGame::new(0);
+    assert_eq!(game.legal_moves().len(), 81);
+    assert_eq!(game.legal_moves()[..2], [vec![0, 0], vec![0, 1]]);
+
+    game.apply_move(&[4, 0]).unwrap();
+    assert_eq!(game.legal_moves().len(), 9);
+    assert!(game.legal_moves().iter().all(|path| path[0] == 0));
+    // wrong board, occupied cell, bad paths
+    assert!(game.apply_move(&[4, 1]).is_err());
+    assert!(game.apply_move(&[0, 9]).is_err());
+    assert!(game.apply_move(&[0]).is_err());
+
+    let path = game.engine_move(90).unwrap();
+    assert_eq!(path[0], 0);
+    assert_eq!(game.state().ply(), 2);
+}
+
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_web_game_takes_win() {
+    // Only 8.7 wins right away, other moves in board 8 win later
+    let state = GameState::from_move_list(
+        "6.6 6.7 7.6 6.3 3.4 4.1 1.4 4.2 2.7 7.5 5.0 0.5 5.7 7.3 3.0 0.0 0.1 1.2 2.5 5.5 \
+         5.6 6.5 5.3 3.2 2.4 4.7 7.8 8.2 2.1 1.0 0.4 4.6 6.1 1.6 6.8 8.8 8.4 4.8 8.1 1.8"
+    ).unwrap();
+    let mut game = WebGame::from_state(state, 1);
+    assert_eq!(game.engine_move(200), Some(vec![8, 7]));
+    assert_eq!(game.state().get_winner(), PlayerMarker::X);
+    assert!(game.legal_moves().is_empty());
+    assert_eq!(game.engine_move(200), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+#[cfg_attr(feature = "board-4x4", ignore = "3x3 fixture")]
+fn test_legal_moves_json() {
+    let mut game = WebGame::new(0);
+    assert!(game.legal_moves_json().starts_with("[[0,0],[0,1],"));
+    game.apply_move(&[4, 0]).unwrap();
+    assert_eq!(game.legal_moves_json(), "[[0,0],[0,1],[0,2],[0,3],[0,4],[0,5],[0,6],[0,7],[0,8]]");
+}
+
+#[test]
+fn test_web_game_is_seeded() {
+    let play = |seed| {
+        let mut game = WebGame::new(seed);
+        (0..4).map(|_| game.engine_move(50).unwrap()).collect::<Vec<_>>()
+    };
+    assert_eq!(play(3), play(3));
+}
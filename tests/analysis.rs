@@ -0,0 +1,47 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// Write a position file to the temp directory
+fn position_file(name: &str, moves: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("meta_tic_tac_toe_{}_{}.txt", name, std::process::id()));
+    fs::write(&path, moves).unwrap();
+    path
+}
+
+#[test]
+fn test_analysis_lists_legal_candidates() {
+    // 4.4 4.0 sends X to board 0
+    let path = position_file("legal", "4.4 4.0\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_project"))
+        .args(["--analysis", path.to_str().unwrap(), "2000"])
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("X to move after 2 plies"));
+    assert!(stdout.contains("Principal variation: 0."));
+
+    let candidates: Vec<&str> = stdout
+        .lines()
+        .skip_while(|line| *line != "Candidates:")
+        .skip(1)
+        .take_while(|line| !line.starts_with("Principal variation"))
+        .map(|line| line.split_whitespace().nth(1).unwrap())
+        .collect();
+    assert_eq!(candidates.len(), 5);
+    for candidate in candidates {
+        assert!(candidate.starts_with("0."), "{} is not on board 0", candidate);
+    }
+}
+
+#[test]
+fn test_analysis_rejects_illegal_position() {
+    let path = position_file("illegal", "4.4 4.4");
+    let output = Command::new(env!("CARGO_BIN_EXE_project"))
+        .args(["--analysis", path.to_str().unwrap(), "10"])
+        .output()
+        .unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(!output.status.success());
+}
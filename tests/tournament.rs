@@ -0,0 +1,40 @@
+use std::{env, process::Command};
+
+use project::{players::RandomPlayer, tournament::run_tournament};
+
+/// The binary only parses the options, the seeded tournament is the one the library plays
+#[test]
+fn test_binary_runs_library_tournament() {
+    let output = Command::new(env!("CARGO_BIN_EXE_project"))
+        .args(["--player1", "random", "--player2", "random", "--games", "4", "--seed", "9"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // Every game gets its own seed, player 1 the even and player 2 the odd ones
+    let (mut game1, mut game2) = (0, 0);
+    let result = run_tournament(
+        || {
+            game1 += 1;
+            Box::new(RandomPlayer::with_seed(9 + 2 * game1))
+        },
+        || {
+            game2 += 1;
+            Box::new(RandomPlayer::with_seed(9 + 2 * game2 + 1))
+        },
+        4,
+    );
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&result.to_string()), "{}", stdout);
+}
+
+#[test]
+fn test_binary_rejects_unknown_player() {
+    let output = Command::new(env!("CARGO_BIN_EXE_project"))
+        .args(["--player1", "minimax:2"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Invalid player 'minimax:2'"));
+}